@@ -0,0 +1,5 @@
+mod player;
+mod synth;
+
+pub use player::Audio;
+pub use synth::Sound;
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::{Context, GameResult};
+
+use crate::settings::AudioSettings;
+
+use super::synth::{self, Sound};
+
+/// Plays the sounds of the terminal
+///
+/// Every call does nothing when the game runs without an audio device, so
+/// the interface never has to check for one.
+pub struct Audio {
+    settings: AudioSettings,
+
+    /// Sounds ready to play, empty without an audio device
+    sources: HashMap<Sound, Source>,
+}
+
+impl Audio {
+    /// Synthesize the sounds and start the ambient hum.
+    ///
+    /// `available` is false when the context was built without audio, in
+    /// which case nothing is ever played.
+    pub fn new(ctx: &mut Context, settings: &AudioSettings, available: bool) -> Self {
+        let mut audio = Self {
+            settings: *settings,
+            sources: HashMap::new(),
+        };
+        if !available {
+            return audio;
+        }
+
+        for sound in Sound::ALL {
+            match Self::load(ctx, sound) {
+                Ok(source) => {
+                    audio.sources.insert(sound, source);
+                }
+                Err(err) => eprintln!("Could not load the {:?} sound: {}", sound, err),
+            }
+        }
+
+        audio.update_hum(ctx);
+        audio
+    }
+
+    fn load(ctx: &mut Context, sound: Sound) -> GameResult<Source> {
+        let data = SoundData::from_bytes(&synth::wav(&sound.samples()));
+        let mut source = Source::from_data(ctx, data)?;
+        source.set_repeat(sound == Sound::Hum);
+        Ok(source)
+    }
+
+    /// Play a sound effect over the ones already playing.
+    pub fn play(&mut self, ctx: &Context, sound: Sound) {
+        if self.settings.muted {
+            return;
+        }
+
+        let volume = self.effects_volume();
+        if let Some(source) = self.sources.get_mut(&sound) {
+            // Detached sinks start at full volume
+            source.set_volume(volume);
+            if let Err(err) = source.play_detached(ctx) {
+                eprintln!("Could not play the {:?} sound: {}", sound, err);
+            }
+        }
+    }
+
+    /// Play the story stinger called `name`, see `Sound::stinger`.
+    pub fn play_stinger(&mut self, ctx: &Context, name: &str) {
+        match Sound::stinger(name) {
+            Some(sound) => self.play(ctx, sound),
+            None => eprintln!("Unknown stinger `{}`", name),
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.settings.muted
+    }
+
+    /// Silence every sound, or bring them back.
+    pub fn set_muted(&mut self, ctx: &Context, muted: bool) {
+        self.settings.muted = muted;
+        self.update_hum(ctx);
+    }
+
+    /// Start the hum at the music volume, or stop it when muted.
+    fn update_hum(&mut self, ctx: &Context) {
+        let volume = self.settings.master_volume * self.settings.music_volume;
+        let muted = self.settings.muted || volume <= 0.0;
+        let hum = match self.sources.get_mut(&Sound::Hum) {
+            Some(hum) => hum,
+            None => return,
+        };
+
+        hum.set_volume(volume.clamp(0.0, 1.0));
+        let result = match (muted, hum.playing()) {
+            (true, true) => hum.stop(ctx),
+            (false, false) => hum.play(ctx),
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            eprintln!("Could not play the ambient hum: {}", err);
+        }
+    }
+
+    fn effects_volume(&self) -> f32 {
+        (self.settings.master_volume * self.settings.effects_volume).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,255 @@
+use std::f32::consts::TAU;
+
+/// Samples per second of every synthesized sound
+pub static SAMPLE_RATE: u32 = 22050;
+
+/// Sounds of the terminal, synthesized when the game starts so no audio
+/// file has to ship with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    /// Typewriter tick of a revealed character
+    Click,
+
+    /// Key pressed at the prompt
+    Key,
+
+    /// Line submitted at the prompt
+    Enter,
+
+    /// Computer hum looping in the background
+    Hum,
+
+    /// Stingers played when the story reaches a dialogue
+    Alert,
+    Granted,
+    Denied,
+    Connect,
+}
+
+/// Names the story uses for the stingers
+static STINGERS: [(&str, Sound); 4] = [
+    ("alert", Sound::Alert),
+    ("granted", Sound::Granted),
+    ("denied", Sound::Denied),
+    ("connect", Sound::Connect),
+];
+
+impl Sound {
+    pub const ALL: [Sound; 8] = [
+        Sound::Click,
+        Sound::Key,
+        Sound::Enter,
+        Sound::Hum,
+        Sound::Alert,
+        Sound::Granted,
+        Sound::Denied,
+        Sound::Connect,
+    ];
+
+    /// Stinger called `name` in the story
+    pub fn stinger(name: &str) -> Option<Sound> {
+        STINGERS
+            .iter()
+            .find(|(stinger, _)| *stinger == name)
+            .map(|(_, sound)| *sound)
+    }
+
+    /// Names of the stingers a story can play
+    pub fn stinger_names() -> Vec<&'static str> {
+        STINGERS.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Waveform of the sound, between -1 and 1
+    pub fn samples(self) -> Vec<f32> {
+        match self {
+            Sound::Click => noise_burst(0.012, 0.5, 0.003),
+            Sound::Key => mix(
+                &noise_burst(0.03, 0.35, 0.008),
+                &tone(180.0, 0.03, 0.4, Wave::Sine),
+            ),
+            Sound::Enter => mix(
+                &noise_burst(0.05, 0.4, 0.012),
+                &tone(120.0, 0.07, 0.5, Wave::Sine),
+            ),
+            Sound::Hum => hum(),
+            Sound::Alert => sequence(
+                &[
+                    (880.0, 0.12),
+                    (0.0, 0.05),
+                    (660.0, 0.12),
+                    (0.0, 0.05),
+                    (880.0, 0.12),
+                ],
+                Wave::Square,
+            ),
+            Sound::Granted => sequence(&[(523.3, 0.09), (659.3, 0.09), (784.0, 0.18)], Wave::Sine),
+            Sound::Denied => sequence(&[(110.0, 0.35)], Wave::Square),
+            Sound::Connect => connect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Wave {
+    Sine,
+    Square,
+}
+
+fn sample_count(seconds: f32) -> usize {
+    (seconds * SAMPLE_RATE as f32).round() as usize
+}
+
+/// Note of `frequency` Hz fading in and out over `seconds`, a zero
+/// frequency is silence
+fn tone(frequency: f32, seconds: f32, volume: f32, wave: Wave) -> Vec<f32> {
+    let count = sample_count(seconds);
+    // Short fades keep the note from popping
+    let fade = sample_count(0.005).min(count / 2).max(1) as f32;
+    (0..count)
+        .map(|i| {
+            if frequency == 0.0 {
+                return 0.0;
+            }
+            let phase = (i as f32 * frequency / SAMPLE_RATE as f32).fract();
+            let value = match wave {
+                Wave::Sine => (phase * TAU).sin(),
+                Wave::Square if phase < 0.5 => 0.6,
+                Wave::Square => -0.6,
+            };
+            let envelope = (i as f32 / fade).min((count - i) as f32 / fade).min(1.0);
+            value * volume * envelope
+        })
+        .collect()
+}
+
+/// Notes played one after the other
+fn sequence(notes: &[(f32, f32)], wave: Wave) -> Vec<f32> {
+    notes
+        .iter()
+        .flat_map(|&(frequency, seconds)| tone(frequency, seconds, 0.5, wave))
+        .collect()
+}
+
+/// Burst of noise decaying with the `decay` time constant
+fn noise_burst(seconds: f32, volume: f32, decay: f32) -> Vec<f32> {
+    // Fixed seed so the sounds are the same on every start
+    let mut state: u32 = 0x2545_f491;
+    (0..sample_count(seconds))
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let noise = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+            let time = i as f32 / SAMPLE_RATE as f32;
+            noise * volume * (-time / decay).exp()
+        })
+        .collect()
+}
+
+/// Sum of two sounds, as long as the longest one
+fn mix(a: &[f32], b: &[f32]) -> Vec<f32> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let sum = a.get(i).unwrap_or(&0.0) + b.get(i).unwrap_or(&0.0);
+            sum.clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+/// Mains hum with its harmonics, a whole number of periods long so it
+/// loops without a click
+fn hum() -> Vec<f32> {
+    (0..SAMPLE_RATE * 2)
+        .map(|i| {
+            let time = i as f32 / SAMPLE_RATE as f32;
+            let wobble = 1.0 + 0.15 * (time * TAU * 0.5).sin();
+            (0.5 * (time * TAU * 60.0).sin()
+                + 0.3 * (time * TAU * 120.0).sin()
+                + 0.1 * (time * TAU * 180.0).sin())
+                * 0.3
+                * wobble
+        })
+        .collect()
+}
+
+/// Modem-like chirps rising in pitch
+fn connect() -> Vec<f32> {
+    let mut samples = Vec::new();
+    for step in 0..6 {
+        let frequency = 1200.0 + step as f32 * 300.0;
+        samples.extend(tone(frequency, 0.05, 0.35, Wave::Square));
+        samples.extend(tone(0.0, 0.02, 0.0, Wave::Sine));
+    }
+    samples
+}
+
+/// Encode `samples` as a mono 16 bit PCM WAV file.
+pub fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend(b"RIFF");
+    bytes.extend((36 + data_size).to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(SAMPLE_RATE.to_le_bytes());
+    bytes.extend((SAMPLE_RATE * 2).to_le_bytes());
+    // Block alignment and bits per sample
+    bytes.extend(2u16.to_le_bytes());
+    bytes.extend(16u16.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(data_size.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sounds_stay_in_range() {
+        for sound in Sound::ALL {
+            let samples = sound.samples();
+            assert!(!samples.is_empty(), "{:?} is silent", sound);
+            assert!(
+                samples.iter().all(|s| (-1.0..=1.0).contains(s)),
+                "{:?} clips",
+                sound
+            );
+        }
+    }
+
+    #[test]
+    fn hum_loops_seamlessly() {
+        let samples = Sound::Hum.samples();
+        let first = samples[0];
+        let last = samples[samples.len() - 1];
+        assert!((first - last).abs() < 0.05);
+    }
+
+    #[test]
+    fn wav_header_describes_the_samples() {
+        let bytes = wav(&[0.0, 1.0, -1.0]);
+
+        assert_eq!(bytes.len(), 44 + 6);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[40..44], &6u32.to_le_bytes());
+        assert_eq!(&bytes[46..48], &i16::MAX.to_le_bytes());
+    }
+
+    #[test]
+    fn stingers_are_found_by_name() {
+        assert_eq!(Sound::stinger("granted"), Some(Sound::Granted));
+        assert_eq!(Sound::stinger("click"), None);
+        assert_eq!(
+            Sound::stinger_names(),
+            vec!["alert", "granted", "denied", "connect"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use ggez::conf::{Conf, FullscreenType};
+use ggez::{GameError, GameResult};
+
+static SETTINGS_FILE: &str = "Settings.toml";
+static RESOURCES_DIR: &str = "static";
+static STORY_FILE: &str = "stories/chapter1.toml";
+
+/// Command line options of the game
+#[derive(Debug, Clone, Parser)]
+#[command(name = "selenialsector-chapter1", about = "Selenial Sector: Chapter 1")]
+pub struct Options {
+    /// Settings file, `Settings.toml` next to the executable by default
+    #[arg(long, value_name = "FILE")]
+    pub settings: Option<PathBuf>,
+
+    /// Directory holding the fonts, stories and themes, `static` next to the
+    /// executable by default
+    #[arg(long, value_name = "DIR")]
+    pub resources: Option<PathBuf>,
+
+    /// Story file, `stories/chapter1.toml` in the resources by default
+    #[arg(long, value_name = "FILE")]
+    pub story: Option<PathBuf>,
+
+    /// Start a new game from this dialogue instead of resuming the save
+    #[arg(long, value_name = "NODE")]
+    pub start: Option<String>,
+
+    /// Save slot to resume from and write to
+    #[arg(long, value_name = "N")]
+    pub slot: Option<u32>,
+
+    /// Run in a window
+    #[arg(long, conflicts_with = "fullscreen")]
+    pub windowed: bool,
+
+    /// Run in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Play the story with the commands of a script, one per line, and
+    /// print the transcript without opening a window
+    #[arg(long, value_name = "SCRIPT")]
+    pub simulate: Option<PathBuf>,
+
+    /// Ask for a username before the simulated story, like a new game does,
+    /// the first lines of the script being the names typed
+    #[arg(long, requires = "simulate")]
+    pub login: bool,
+}
+
+impl Options {
+    /// Settings file to read, located like the resources when not given.
+    pub fn settings_path(&self) -> GameResult<PathBuf> {
+        match &self.settings {
+            Some(path) => require(path, "Settings file"),
+            None => locate(SETTINGS_FILE, "Settings file", "--settings"),
+        }
+    }
+
+    pub fn resources_dir(&self) -> GameResult<PathBuf> {
+        match &self.resources {
+            Some(path) => require(path, "Resource directory"),
+            None => locate(RESOURCES_DIR, "Resource directory", "--resources"),
+        }
+    }
+
+    pub fn story_path(&self, resources_dir: &Path) -> GameResult<PathBuf> {
+        match &self.story {
+            Some(path) => require(path, "Story file"),
+            None => require(&resources_dir.join(STORY_FILE), "Story file"),
+        }
+    }
+
+    /// Apply the window mode flags over the settings file.
+    pub fn apply(&self, conf: &mut Conf) {
+        if self.fullscreen {
+            conf.window_mode.fullscreen_type = FullscreenType::Desktop;
+        } else if self.windowed {
+            conf.window_mode.fullscreen_type = FullscreenType::Windowed;
+        }
+    }
+}
+
+fn require(path: &Path, what: &str) -> GameResult<PathBuf> {
+    match path.exists() {
+        true => Ok(path.to_path_buf()),
+        false => Err(GameError::ConfigError(format!(
+            "{} `{}` does not exist",
+            what,
+            path.display()
+        ))),
+    }
+}
+
+/// Find `name` next to the executable, in the crate directory when run
+/// through cargo, or in the working directory.
+fn locate(name: &str, what: &str, option: &str) -> GameResult<PathBuf> {
+    let candidates = search_dirs().into_iter().map(|dir| dir.join(name));
+    find_first(candidates, what, option)
+}
+
+fn find_first<I>(candidates: I, what: &str, option: &str) -> GameResult<PathBuf>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let mut searched = Vec::new();
+    for path in candidates {
+        if path.exists() {
+            return Ok(path);
+        }
+        searched.push(path);
+    }
+
+    let searched: Vec<String> = searched
+        .iter()
+        .map(|path| format!("  {}", path.display()))
+        .collect();
+    Err(GameError::ConfigError(format!(
+        "{} not found, pass its location with {}. Searched in:\n{}",
+        what,
+        option,
+        searched.join("\n")
+    )))
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(exe) = env::current_exe() {
+        dirs.extend(exe.parent().map(Path::to_path_buf));
+    }
+    if let Ok(dir) = env::var("CARGO_MANIFEST_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Ok(dir) = env::current_dir() {
+        dirs.push(dir);
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, clap::Error> {
+        Options::try_parse_from(std::iter::once("game").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn parses_all_options() {
+        let options = parse(&[
+            "--settings", "custom.toml",
+            "--resources", "assets",
+            "--story", "story.toml",
+            "--start", "tea",
+            "--slot", "3",
+            "--fullscreen",
+        ])
+        .unwrap();
+
+        assert_eq!(options.settings, Some(PathBuf::from("custom.toml")));
+        assert_eq!(options.resources, Some(PathBuf::from("assets")));
+        assert_eq!(options.story, Some(PathBuf::from("story.toml")));
+        assert_eq!(options.start.as_deref(), Some("tea"));
+        assert_eq!(options.slot, Some(3));
+        assert!(options.fullscreen);
+    }
+
+    #[test]
+    fn window_modes_conflict() {
+        assert!(parse(&["--windowed", "--fullscreen"]).is_err());
+    }
+
+    #[test]
+    fn window_flags_override_settings() {
+        let mut conf = Conf::new();
+        conf.window_mode.fullscreen_type = FullscreenType::Desktop;
+
+        parse(&["--windowed"]).unwrap().apply(&mut conf);
+        assert_eq!(conf.window_mode.fullscreen_type, FullscreenType::Windowed);
+
+        parse(&[]).unwrap().apply(&mut conf);
+        assert_eq!(conf.window_mode.fullscreen_type, FullscreenType::Windowed);
+    }
+
+    #[test]
+    fn missing_resources_list_searched_paths() {
+        let missing = PathBuf::from("does-not-exist/static");
+        let err = find_first(vec![missing], "Resource directory", "--resources").unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("Resource directory not found"));
+        assert!(message.contains("--resources"));
+        assert!(message.contains("does-not-exist/static"));
+    }
+
+    #[test]
+    fn first_existing_candidate_is_used() {
+        let candidates = vec![
+            PathBuf::from("does-not-exist"),
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")),
+        ];
+
+        assert_eq!(
+            find_first(candidates, "Directory", "--dir").unwrap(),
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::state::{Value, Variables};
+
+/// Comparison between a story variable and a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Eq => "==",
+            Operator::NotEq => "!=",
+            Operator::Less => "<",
+            Operator::LessEq => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEq => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Condition over story variables that decides if a decision is offered
+///
+/// Conditions are written in story files as small expressions:
+/// `asked_for_tea`, `!asked_for_tea`, `drink == "milk"`, `cups >= 2`,
+/// combined with `&&`, `||` and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// True when the variable is set to a truthy value
+    Is(String),
+    Compare(String, Operator, Value),
+    Not(Box<Condition>),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ConditionError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, cursor: 0 };
+        let condition = parser.any()?;
+
+        match parser.peek() {
+            None => Ok(condition),
+            Some(token) => Err(ConditionError(format!("unexpected `{}`", token))),
+        }
+    }
+
+    pub fn holds(&self, variables: &Variables) -> bool {
+        match self {
+            Condition::Is(name) => variables.get(name).is_some_and(Value::is_truthy),
+            Condition::Compare(name, op, value) => match variables.get(name) {
+                Some(current) => compare(current, *op, value),
+                None => *op == Operator::NotEq,
+            },
+            Condition::Not(inner) => !inner.holds(variables),
+            Condition::All(parts) => parts.iter().all(|c| c.holds(variables)),
+            Condition::Any(parts) => parts.iter().any(|c| c.holds(variables)),
+        }
+    }
+}
+
+fn compare(current: &Value, op: Operator, expected: &Value) -> bool {
+    let ordering = match (current, expected) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Flag(a), Value::Flag(b)) => a.cmp(b),
+        _ => return op == Operator::NotEq,
+    };
+
+    match op {
+        Operator::Eq => ordering == Ordering::Equal,
+        Operator::NotEq => ordering != Ordering::Equal,
+        Operator::Less => ordering == Ordering::Less,
+        Operator::LessEq => ordering != Ordering::Greater,
+        Operator::Greater => ordering == Ordering::Greater,
+        Operator::GreaterEq => ordering != Ordering::Less,
+    }
+}
+
+/// Error raised for a condition that cannot be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionError(pub String);
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Literal(Value),
+    Op(Operator),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Literal(value) => write!(f, "{}", value),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Not => write!(f, "!"),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut cursor = 0;
+
+    while cursor < chars.len() {
+        let current = chars[cursor];
+        let next = chars.get(cursor + 1).copied();
+
+        let (token, size) = match (current, next) {
+            (c, _) if c.is_whitespace() => {
+                cursor += 1;
+                continue;
+            }
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(Operator::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Operator::NotEq), 2),
+            ('<', Some('=')) => (Token::Op(Operator::LessEq), 2),
+            ('>', Some('=')) => (Token::Op(Operator::GreaterEq), 2),
+            ('<', _) => (Token::Op(Operator::Less), 1),
+            ('>', _) => (Token::Op(Operator::Greater), 1),
+            ('!', _) => (Token::Not, 1),
+            ('"', _) | ('\'', _) => {
+                let end = chars[cursor + 1..]
+                    .iter()
+                    .position(|c| *c == current)
+                    .ok_or_else(|| ConditionError("unterminated string".to_string()))?;
+                let text: String = chars[cursor + 1..cursor + 1 + end].iter().collect();
+                (Token::Literal(Value::Text(text)), end + 2)
+            }
+            (c, _) if c.is_ascii_digit() || c == '-' => {
+                let size = 1 + chars[cursor + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let number: String = chars[cursor..cursor + size].iter().collect();
+                let number = number
+                    .parse()
+                    .map_err(|_| ConditionError(format!("invalid number `{}`", number)))?;
+                (Token::Literal(Value::Int(number)), size)
+            }
+            (c, _) if c.is_alphanumeric() || c == '_' => {
+                let size = chars[cursor..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                let word: String = chars[cursor..cursor + size].iter().collect();
+                let token = match word.as_str() {
+                    "true" => Token::Literal(Value::Flag(true)),
+                    "false" => Token::Literal(Value::Flag(false)),
+                    _ => Token::Name(word),
+                };
+                (token, size)
+            }
+            (c, _) => return Err(ConditionError(format!("unexpected `{}`", c))),
+        };
+
+        tokens.push(token);
+        cursor += size;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.cursor).cloned();
+        self.cursor += 1;
+        token
+    }
+
+    fn any(&mut self) -> Result<Condition, ConditionError> {
+        let mut parts = vec![self.all()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            parts.push(self.all()?);
+        }
+
+        Ok(match parts.len() {
+            1 => parts.remove(0),
+            _ => Condition::Any(parts),
+        })
+    }
+
+    fn all(&mut self) -> Result<Condition, ConditionError> {
+        let mut parts = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            parts.push(self.unary()?);
+        }
+
+        Ok(match parts.len() {
+            1 => parts.remove(0),
+            _ => Condition::All(parts),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Condition, ConditionError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Condition::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.any()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(ConditionError("missing `)`".to_string())),
+                }
+            }
+            Some(Token::Name(name)) => self.comparison(name),
+            Some(token) => Err(ConditionError(format!("unexpected `{}`", token))),
+            None => Err(ConditionError("unexpected end of condition".to_string())),
+        }
+    }
+
+    fn comparison(&mut self, name: String) -> Result<Condition, ConditionError> {
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => return Ok(Condition::Is(name)),
+        };
+        self.next();
+
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(Condition::Compare(name, op, value)),
+            _ => Err(ConditionError(format!("`{}` must be compared to a value", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::new();
+        variables.insert("asked".to_string(), Value::Flag(true));
+        variables.insert("drink".to_string(), Value::Text("milk".to_string()));
+        variables.insert("cups".to_string(), Value::Int(2));
+        variables.insert("debt".to_string(), Value::Int(-3));
+        variables
+    }
+
+    fn holds(source: &str) -> bool {
+        Condition::parse(source).unwrap().holds(&variables())
+    }
+
+    fn error(source: &str) -> String {
+        Condition::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let name = |name: &str| Condition::Is(name.to_string());
+        assert_eq!(
+            Condition::parse("a || b && c").unwrap(),
+            Condition::Any(vec![name("a"), Condition::All(vec![name("b"), name("c")])])
+        );
+        assert_eq!(
+            Condition::parse("(a || b) && c").unwrap(),
+            Condition::All(vec![Condition::Any(vec![name("a"), name("b")]), name("c")])
+        );
+
+        assert!(holds("missing || asked && cups == 2"));
+        assert!(!holds("(missing || asked) && cups == 3"));
+    }
+
+    #[test]
+    fn negation_and_comparisons() {
+        assert!(holds("asked"));
+        assert!(!holds("!asked"));
+        assert!(holds("!!asked && !missing"));
+        assert!(holds("drink == \"milk\" && drink != 'tea'"));
+        assert!(holds("cups >= 2 && cups <= 2 && cups > 1 && cups < 3"));
+        assert!(holds("debt == -3 && debt < 0"));
+        assert!(holds("asked == true"));
+    }
+
+    #[test]
+    fn mismatched_types_and_missing_variables_only_differ() {
+        assert!(!holds("cups == \"2\""));
+        assert!(holds("cups != \"2\""));
+        assert!(!holds("drink > 1"));
+
+        assert!(!holds("missing == 1"));
+        assert!(!holds("missing < 1"));
+        assert!(holds("missing != 1"));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(error("(asked && cups > 1"), "missing `)`");
+        assert_eq!(error("drink == \"milk"), "unterminated string");
+        assert_eq!(error("cups >= >= 2"), "`cups` must be compared to a value");
+        assert_eq!(error(">= 2"), "unexpected `>=`");
+        assert_eq!(error("asked cups"), "unexpected `cups`");
+        assert_eq!(error("asked &&"), "unexpected end of condition");
+        assert_eq!(error("cups == -"), "invalid number `-`");
+        assert_eq!(error("asked & cups"), "unexpected `&`");
+    }
+}
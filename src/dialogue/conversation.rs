@@ -0,0 +1,375 @@
+use std::time::Duration;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::utils::{StyledLine, TextBox, TextMeasure};
+
+use super::dialogue::normalize_command;
+use super::onboarding::{self, Profile, PLAYER_VARIABLE, TERMINAL_VARIABLE};
+use super::shell::Shell;
+use super::state::{Value, Variables};
+use super::{Dialogue, Story};
+
+/// Line ending the conversation once no decisions are left, like an
+/// empty line
+static EXIT_COMMAND: &str = "exit";
+
+/// Outcome of submitting a command to a conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Nothing changed in the conversation
+    Idle,
+
+    /// The conversation moved to a new dialogue
+    Advanced,
+
+    /// A shell command printed its output
+    Printed,
+
+    /// A shell command cleared the screen before printing its output
+    Cleared,
+
+    /// The player left a dialogue with no decisions left
+    Ended,
+}
+
+/// Walk through a story graph
+///
+/// Keeps the current dialogue and the story variables, and knows nothing
+/// about how the dialogue text is drawn so it can run without a window.
+pub struct Conversation {
+    story: Story,
+    variables: Variables,
+    current_dialogue: Rc<RefCell<Dialogue>>,
+    shell: Shell,
+
+    /// Output of the last shell command or login prompt and whether it is
+    /// revealed
+    output: Option<(TextBox, bool)>,
+
+    /// Seed of the terminal id while the player is asked for a username
+    login_seed: Option<u64>,
+}
+
+impl Conversation {
+    pub fn new(story: Story) -> Self {
+        let mut variables = story.initial_variables();
+        let current_dialogue = story.start();
+        current_dialogue.borrow().enter(&mut variables);
+        current_dialogue.borrow_mut().reset(&variables);
+
+        Self {
+            shell: Shell::new(story.filesystem(), story.commands()),
+            story,
+            variables,
+            current_dialogue,
+            output: None,
+            login_seed: None,
+        }
+    }
+
+    /// Ask the player for a username before the story starts.
+    ///
+    /// The terminal id given to the player is derived from `seed`.
+    pub fn start_onboarding(&mut self, seed: u64) {
+        self.login_seed = Some(seed);
+        self.print(onboarding::LOGIN_PROMPT.to_string());
+    }
+
+    /// Whether the player is still choosing a username
+    pub fn is_onboarding(&self) -> bool {
+        self.login_seed.is_some()
+    }
+
+    /// Name and terminal of the player, once chosen
+    pub fn profile(&self) -> Option<Profile> {
+        match (self.variables.get(PLAYER_VARIABLE), self.variables.get(TERMINAL_VARIABLE)) {
+            (Some(player), Some(terminal)) => Some(Profile {
+                player: player.to_string(),
+                terminal: terminal.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Store the player's identity in the `player` and `terminal` variables
+    /// so story text can mention them.
+    ///
+    /// The current dialogue is revealed again with the new names.
+    pub fn set_profile(&mut self, profile: &Profile) {
+        self.variables
+            .insert(PLAYER_VARIABLE.to_string(), Value::Text(profile.player.to_owned()));
+        self.variables
+            .insert(TERMINAL_VARIABLE.to_string(), Value::Text(profile.terminal.to_owned()));
+        self.shell.set_user(&profile.player);
+        self.current_dialogue.borrow_mut().reset(&self.variables);
+    }
+
+    pub fn story_version(&self) -> u32 {
+        self.story.version()
+    }
+
+    /// Id of the current dialogue
+    pub fn current_id(&self) -> String {
+        self.current_dialogue.borrow().get_id().to_string()
+    }
+
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Working directory of the shell
+    pub fn cwd(&self) -> &str {
+        self.shell.cwd()
+    }
+
+    /// Stinger of the current dialogue, played when it is reached
+    pub fn sound(&self) -> Option<String> {
+        self.current_dialogue.borrow().get_sound().map(str::to_string)
+    }
+
+    /// Lines of the last command output, or of the current dialogue when
+    /// no command printed since it was reached, revealed so far
+    pub fn lines(&self) -> Vec<StyledLine> {
+        match &self.output {
+            // Commands like `cd` print nothing at all, not even a blank line
+            Some((output, _)) if output.lines == [StyledLine::new()] => Vec::new(),
+            Some((output, _)) => output.lines.to_vec(),
+            None => self.current_dialogue.borrow().lines().to_vec(),
+        }
+    }
+
+    /// Whether the whole text of the current dialogue and of the command
+    /// output is displayed
+    pub fn is_revealed(&self) -> bool {
+        if self.is_onboarding() {
+            return self.output.as_ref().is_some_and(|(_, revealed)| *revealed);
+        }
+
+        let output_revealed = self.output.as_ref().is_none_or(|(_, revealed)| *revealed);
+        self.current_dialogue.borrow().has_text_updated && output_revealed
+    }
+
+    /// Whether the current dialogue has no decisions left to take
+    pub fn is_ending(&self) -> bool {
+        !self.is_onboarding()
+            && self
+                .current_dialogue
+                .borrow()
+                .available_decisions(&self.variables)
+                .is_empty()
+    }
+
+    /// Commands leading to the decisions currently available
+    pub fn decisions(&self) -> Vec<String> {
+        if self.is_onboarding() {
+            return Vec::new();
+        }
+
+        self.current_dialogue
+            .borrow()
+            .available_decisions(&self.variables)
+            .iter()
+            .filter_map(|decision| decision.borrow().get_command())
+            .collect()
+    }
+
+    /// Commands leading to the decisions currently available, followed by
+    /// the shell commands
+    pub fn commands(&self) -> Vec<String> {
+        if self.is_onboarding() {
+            return Vec::new();
+        }
+
+        let mut commands = self.decisions();
+        commands.extend(self.shell.completions(&self.variables));
+        if self.is_ending() {
+            commands.push(EXIT_COMMAND.to_string());
+        }
+        commands
+    }
+
+    /// Reveal the current dialogue text, then the command output, for the
+    /// `elapsed` time.
+    ///
+    /// Returns the number of characters revealed.
+    pub fn update(&mut self, elapsed: Duration, measure: &dyn TextMeasure) -> usize {
+        if !self.is_onboarding() && !self.current_dialogue.borrow().has_text_updated {
+            self.current_dialogue.borrow_mut().update(elapsed, measure)
+        } else if let Some((output, revealed)) = &mut self.output {
+            *revealed = output.update(elapsed, measure).is_none();
+            output.revealed()
+        } else {
+            0
+        }
+    }
+
+    /// Show the rest of the current dialogue text and command output at once.
+    pub fn skip(&mut self, measure: &dyn TextMeasure) {
+        if !self.is_onboarding() {
+            self.current_dialogue.borrow_mut().skip(measure);
+        }
+        if let Some((output, revealed)) = &mut self.output {
+            output.complete(measure);
+            *revealed = true;
+        }
+    }
+
+    /// Wrap the dialogue text and command output to a new `width`.
+    pub fn resize(&mut self, width: f32, measure: &dyn TextMeasure) {
+        self.story.resize(width, measure);
+        if let Some((output, _)) = &mut self.output {
+            output.resize(width, measure);
+        }
+    }
+
+    /// Continue the conversation from the dialogue with id `node`.
+    ///
+    /// Saved variables are applied over the story's initial ones so
+    /// variables added by newer story versions keep their defaults.
+    /// Returns `false` and starts from the beginning of the story when the
+    /// node does not exist anymore. The shell goes back to the working
+    /// directory `cwd` when it can still be entered, see `Shell::set_cwd`.
+    pub fn restore(&mut self, node: &str, variables: Variables, cwd: Option<&str>) -> bool {
+        let (dialogue, found) = match self.story.get(node) {
+            Some(d) => (d, true),
+            None => (self.story.start(), false),
+        };
+
+        self.variables = self.story.initial_variables();
+        if found {
+            self.variables.extend(variables);
+            if let Some(cwd) = cwd {
+                self.shell.set_cwd(cwd, &self.variables);
+            }
+        } else {
+            dialogue.borrow().enter(&mut self.variables);
+        }
+
+        self.current_dialogue = dialogue;
+        self.current_dialogue.borrow_mut().reset(&self.variables);
+        self.output = None;
+        self.login_seed = None;
+        found
+    }
+
+    /// Pick the decision matching the typed `command`, or run it as a
+    /// shell command when no decision matches.
+    ///
+    /// Once no decisions are left, only an empty line or `exit` ends the
+    /// conversation. Commands are ignored until the current dialogue text
+    /// is revealed.
+    pub fn submit(&mut self, command: &str) -> Progress {
+        if !self.is_revealed() {
+            return Progress::Idle;
+        }
+        if let Some(seed) = self.login_seed {
+            return self.login(command, seed);
+        }
+        if self.is_ending() {
+            return match normalize_command(command).as_str() {
+                "" => Progress::Ended,
+                command if command == EXIT_COMMAND => Progress::Ended,
+                _ => self.execute(command),
+            };
+        }
+
+        let next_dialogue_result = self
+            .current_dialogue
+            .borrow()
+            .advance(command, &self.variables);
+        if !next_dialogue_result.0 {
+            return self.execute(command);
+        }
+
+        match next_dialogue_result.1 {
+            Some(new_dialogue) => {
+                self.current_dialogue = new_dialogue;
+                self.current_dialogue.borrow().enter(&mut self.variables);
+                self.current_dialogue.borrow_mut().reset(&self.variables);
+                self.output = None;
+                Progress::Advanced
+            }
+            None => Progress::Ended,
+        }
+    }
+
+    fn execute(&mut self, command: &str) -> Progress {
+        let output = match self.shell.execute(command, &self.variables) {
+            Some(output) => output,
+            None => return Progress::Idle,
+        };
+
+        self.variables.extend(output.set);
+        self.print(output.text);
+        match output.clear {
+            true => Progress::Cleared,
+            false => Progress::Printed,
+        }
+    }
+
+    /// Log the player in as `name`, or ask again when the name is refused.
+    fn login(&mut self, name: &str, seed: u64) -> Progress {
+        match onboarding::login(name, seed) {
+            Ok(profile) => {
+                self.login_seed = None;
+                self.output = None;
+                self.set_profile(&profile);
+                Progress::Advanced
+            }
+            Err(err) => {
+                self.print(onboarding::retry_prompt(err));
+                Progress::Printed
+            }
+        }
+    }
+
+    fn print(&mut self, text: String) {
+        self.output = Some((self.story.text_box(text), false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialogue::StoryFile;
+    use crate::utils::layout::MonoMeasure;
+
+    static MONO: MonoMeasure = MonoMeasure { advance: 1.0 };
+
+    fn submit(conversation: &mut Conversation, command: &str) -> Progress {
+        let progress = conversation.submit(command);
+        conversation.skip(&MONO);
+        progress
+    }
+
+    #[test]
+    fn shell_still_runs_once_no_decisions_are_left() {
+        let story = StoryFile::parse(
+            r#"
+            start = "intro"
+
+            [[nodes]]
+            id = "intro"
+            text = "connected"
+            decisions = ["logout"]
+
+            [[nodes]]
+            id = "logout"
+            text = "bye"
+            command = "logout"
+            "#,
+        )
+        .unwrap();
+        let mut conversation = Conversation::new(story.build(80.0, 0.0));
+        conversation.skip(&MONO);
+
+        assert_eq!(submit(&mut conversation, "logout"), Progress::Advanced);
+        assert!(conversation.is_ending());
+        assert!(conversation.commands().contains(&"exit".to_string()));
+
+        assert_eq!(submit(&mut conversation, "pwd"), Progress::Printed);
+        assert_eq!(submit(&mut conversation, "logotu"), Progress::Idle);
+        assert_eq!(submit(&mut conversation, "EXIT"), Progress::Ended);
+        assert_eq!(submit(&mut conversation, ""), Progress::Ended);
+    }
+}
@@ -1,75 +1,119 @@
+use std::time::Duration;
 use std::{cell::RefCell, rc::Rc};
 
-use ggez::event::KeyCode;
-use ggez::graphics::Font;
-use ggez::{Context, GameResult};
+use crate::utils::{StyledLine, TextBox, TextMeasure};
 
-use crate::utils::Point2D;
-use crate::utils::TextBox;
+use super::condition::Condition;
+use super::state::{self, Variables};
 
-type Decisions = Vec<Rc<RefCell<Dialogue>>>;
+pub type Decisions = Vec<Rc<RefCell<Dialogue>>>;
 pub type CheckedDecision = (bool, Option<Rc<RefCell<Dialogue>>>);
 
 #[derive(Debug)]
 pub struct Dialogue {
+    /// Unique identifier of the node in the story graph
+    id: String,
     pub has_text_updated: bool,
+    /// Text as written in the story, before variables are interpolated
+    text: String,
     textbox: TextBox,
-    pub start_position: Point2D,
-    required_input: KeyCode,
+    /// Command the player types to pick this dialogue from its parent,
+    /// normalized once when the dialogue is built
+    command: Option<String>,
+    /// Condition that must hold for this dialogue to be offered as a decision
+    condition: Option<Condition>,
+    /// Story variables set when the conversation reaches this dialogue
+    on_enter: Variables,
+    /// Stinger played when the conversation reaches this dialogue
+    sound: Option<String>,
     decisions: Option<Decisions>,
 }
 impl Dialogue {
     pub fn new(
+        id: String,
         dialogue_text: String,
         decisions: Option<Decisions>,
-        req_input: Option<KeyCode>,
+        command: Option<String>,
         width: f32,
-        dialogue_font: Option<Font>,
-        font_size: f32,
-        start_position: Option<Point2D>,
+        text_speed: f32,
     ) -> Dialogue {
-        let required_input = match req_input {
-            Some(key) => key,
-            None => KeyCode::Escape,
-        };
-
-        let font = match dialogue_font {
-            Some(f) => f,
-            None => Font::default(),
-        };
-
-        let start_vector = match start_position {
-            Some(p) => p,
-            None => Point2D::new(0.0, 0.0),
-        };
-
         Dialogue {
-            textbox: TextBox::new(width, font, font_size, dialogue_text, start_vector.clone()),
-            required_input,
+            id,
+            textbox: TextBox::new(width, dialogue_text.to_owned(), text_speed),
+            text: dialogue_text,
+            command: command.map(|c| normalize_command(&c)),
+            condition: None,
+            on_enter: Variables::new(),
+            sound: None,
             decisions,
-            start_position: start_vector,
             has_text_updated: false
         }
     }
 
-    /// Increment position by x and y
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Command typed to reach this dialogue, as matched by `advance`
+    pub fn get_command(&self) -> Option<String> {
+        self.command.to_owned()
+    }
+
+    /// Replace the decisions that lead out of this dialogue.
     ///
-    /// The new position will be the current position + the new point coordinates
-    /// given as parameters.
-    pub fn set_position(&mut self, new_pos: Point2D) {
-        self.textbox.display_cursor = new_pos;
-        self.start_position = new_pos;
-        self.textbox.lines = vec![(self.start_position, "".to_string())];
+    /// Used when building a graph where nodes may reference each other
+    /// before all of them exist.
+    pub fn set_decisions(&mut self, decisions: Option<Decisions>) {
+        self.decisions = decisions;
+    }
+
+    pub fn set_condition(&mut self, condition: Option<Condition>) {
+        self.condition = condition;
     }
 
-    /// Display method that displays to the game GUI the content value of the Dialogue
-    /// using the display position
-    pub fn display(&self, ctx: &mut Context) -> GameResult {
-        self.textbox.display(ctx)
+    pub fn set_on_enter(&mut self, variables: Variables) {
+        self.on_enter = variables;
     }
 
-    pub fn update(&mut self) {
-        match self.textbox.update() {
+    pub fn set_sound(&mut self, sound: Option<String>) {
+        self.sound = sound;
+    }
+
+    pub fn get_sound(&self) -> Option<&str> {
+        self.sound.as_deref()
+    }
+
+    /// Apply the variables this dialogue sets once it is reached.
+    pub fn enter(&self, variables: &mut Variables) {
+        for (name, value) in self.on_enter.iter() {
+            variables.insert(name.to_owned(), value.clone());
+        }
+    }
+
+    /// Whether this dialogue can currently be picked from its parent
+    pub fn is_available(&self, variables: &Variables) -> bool {
+        match &self.condition {
+            Some(condition) => condition.holds(variables),
+            None => true,
+        }
+    }
+
+    /// Start revealing the dialogue text from the beginning, with the
+    /// current value of the variables it mentions.
+    pub fn reset(&mut self, variables: &Variables) {
+        self.textbox.set_text(state::interpolate(&self.text, variables));
+        self.has_text_updated = false;
+    }
+
+    /// Lines of the dialogue text revealed so far
+    pub fn lines(&self) -> &[StyledLine] {
+        &self.textbox.lines
+    }
+
+    /// Reveal the text due in the `elapsed` time, returns the number of
+    /// characters revealed.
+    pub fn update(&mut self, elapsed: Duration, measure: &dyn TextMeasure) -> usize {
+        match self.textbox.update(elapsed, measure) {
             Some(_) => {
                 self.has_text_updated = false
             },
@@ -77,20 +121,48 @@ impl Dialogue {
                 self.has_text_updated = true
             }
         }
+        self.textbox.revealed()
     }
 
-    /// Based on the user input advances the dialogue to the next dialogue
-    /// part of the conversation.
-    pub fn advance(&self, user_input: KeyCode) -> CheckedDecision {
-        let decisions = match &self.decisions {
-            Some(d) => d,
-            None => return (true, None),
-        };
+    /// Wrap the dialogue text to a new `width`, see `TextBox::resize`.
+    pub fn resize(&mut self, width: f32, measure: &dyn TextMeasure) {
+        self.textbox.resize(width, measure);
+    }
+
+    /// Show the rest of the dialogue text at once.
+    pub fn skip(&mut self, measure: &dyn TextMeasure) {
+        self.textbox.complete(measure);
+        self.has_text_updated = true;
+    }
 
+    /// Decisions whose condition holds for the given variables
+    pub fn available_decisions(&self, variables: &Variables) -> Decisions {
+        match &self.decisions {
+            Some(decisions) => decisions
+                .iter()
+                .filter(|d| d.borrow().is_available(variables))
+                .map(Rc::clone)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Based on the command typed by the user advances the dialogue to the
+    /// next dialogue part of the conversation.
+    ///
+    /// Only decisions available for the current variables are considered,
+    /// and a dialogue with none left ends the conversation.
+    pub fn advance(&self, user_input: &str, variables: &Variables) -> CheckedDecision {
+        let decisions = self.available_decisions(variables);
+        if decisions.is_empty() {
+            return (true, None);
+        }
+
+        let user_input = normalize_command(user_input);
         let mut matches = false;
         let mut action_index = usize::MAX;
         for (pos, act) in decisions.iter().enumerate() {
-            if act.borrow().required_input == user_input {
+            if act.borrow().command.as_deref() == Some(user_input.as_str()) {
                 matches = true;
                 action_index = pos;
                 break;
@@ -101,13 +173,16 @@ impl Dialogue {
             return (true, Some(decisions[action_index].clone()));
         }
 
-        return (false, None);
+        (false, None)
     }
+}
 
-    pub fn get_last_display_pos(&self) -> Point2D {
-        match self.textbox.lines.last() {
-            Some(line) => line.0,
-            None => self.start_position
-        }
-    }
+/// Lowercase a command and collapse the whitespace between its words so
+/// `SSH   10.0.0.4` and `ssh 10.0.0.4` are the same command.
+pub fn normalize_command(command: &str) -> String {
+    command
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
 }
@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::condition::Condition;
+use super::state::Variables;
+
+static ROOT: &str = "/";
+
+/// Virtual filesystem as written in the story file
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileSystemDef {
+    /// Directory the player starts in and `~` points to
+    #[serde(default = "root_path")]
+    pub home: String,
+
+    /// Directories that are empty or need a permission, parents of files
+    /// are created automatically
+    #[serde(default)]
+    pub directories: Vec<DirectoryDef>,
+
+    #[serde(default)]
+    pub files: Vec<FileDef>,
+}
+
+impl Default for FileSystemDef {
+    fn default() -> Self {
+        Self {
+            home: root_path(),
+            directories: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+}
+
+fn root_path() -> String {
+    ROOT.to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DirectoryDef {
+    /// Absolute path of the directory
+    pub path: String,
+
+    /// Condition that must hold to enter or list the directory
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileDef {
+    /// Absolute path of the file
+    pub path: String,
+
+    /// Content of the file, may use the same markup as dialogue text
+    pub text: String,
+
+    /// Condition that must hold to read the file
+    pub condition: Option<String>,
+
+    /// Story variables set every time the file is read
+    #[serde(default)]
+    pub set: Variables,
+}
+
+/// Errors returned when accessing the filesystem, worded like a shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsError {
+    NotFound,
+    PermissionDenied,
+    NotADirectory,
+    IsADirectory,
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::NotFound => write!(f, "No such file or directory"),
+            FsError::PermissionDenied => write!(f, "Permission denied"),
+            FsError::NotADirectory => write!(f, "Not a directory"),
+            FsError::IsADirectory => write!(f, "Is a directory"),
+        }
+    }
+}
+
+/// Readable file of the virtual filesystem
+#[derive(Debug, Clone)]
+pub struct File {
+    pub text: String,
+    pub set: Variables,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    Directory(BTreeMap<String, Entry>, Option<Condition>),
+    File(File, Option<Condition>),
+}
+
+impl Entry {
+    fn is_accessible(&self, variables: &Variables) -> bool {
+        let condition = match self {
+            Entry::Directory(_, condition) | Entry::File(_, condition) => condition,
+        };
+        condition.as_ref().is_none_or(|c| c.holds(variables))
+    }
+}
+
+/// Tree of directories and text files the player can browse
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    root: Entry,
+    home: String,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self {
+            root: Entry::Directory(BTreeMap::new(), None),
+            home: root_path(),
+        }
+    }
+}
+
+impl FileSystem {
+    /// Build the tree described by `def`.
+    ///
+    /// Fails with a message when a path is not absolute, is defined twice,
+    /// goes through a file or when a condition is invalid.
+    pub fn build(def: &FileSystemDef) -> Result<Self, String> {
+        let mut fs = Self::default();
+
+        for dir in def.directories.iter() {
+            let condition = parse_condition(&dir.path, dir.condition.as_deref())?;
+            fs.insert(&dir.path, Entry::Directory(BTreeMap::new(), condition))?;
+        }
+        for file in def.files.iter() {
+            let condition = parse_condition(&file.path, file.condition.as_deref())?;
+            let entry = Entry::File(
+                File {
+                    text: file.text.to_owned(),
+                    set: file.set.clone(),
+                },
+                condition,
+            );
+            fs.insert(&file.path, entry)?;
+        }
+
+        fs.home = normalize(ROOT, ROOT, &def.home);
+        match fs.lookup(&fs.home) {
+            Some(Entry::Directory(..)) => Ok(fs),
+            _ => Err(format!("home `{}` is not a directory", def.home)),
+        }
+    }
+
+    fn insert(&mut self, path: &str, entry: Entry) -> Result<(), String> {
+        if !path.starts_with(ROOT) {
+            return Err(format!("path `{}` is not absolute", path));
+        }
+
+        let names = components(path);
+        let (name, parents) = match names.split_last() {
+            Some(split) => split,
+            None => return Err(format!("path `{}` has no name", path)),
+        };
+
+        let mut children = match &mut self.root {
+            Entry::Directory(children, _) => children,
+            Entry::File(..) => unreachable!("the root is a directory"),
+        };
+        for parent in parents {
+            let dir = children
+                .entry(parent.to_string())
+                .or_insert_with(|| Entry::Directory(BTreeMap::new(), None));
+            children = match dir {
+                Entry::Directory(children, _) => children,
+                Entry::File(..) => return Err(format!("path `{}` goes through a file", path)),
+            };
+        }
+
+        // Directories created as the parent of a file may be declared later
+        // to set their permission
+        match (children.get_mut(*name), entry) {
+            (None, entry) => {
+                children.insert(name.to_string(), entry);
+            }
+            (Some(Entry::Directory(_, existing @ None)), Entry::Directory(_, condition)) => {
+                *existing = condition;
+            }
+            _ => return Err(format!("path `{}` is defined twice", path)),
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, path: &str) -> Option<&Entry> {
+        let mut entry = &self.root;
+        for name in components(path) {
+            entry = match entry {
+                Entry::Directory(children, _) => children.get(name)?,
+                Entry::File(..) => return None,
+            };
+        }
+        Some(entry)
+    }
+
+    /// Find the entry at the absolute `path`, checking the permission of
+    /// every directory on the way.
+    fn access(&self, path: &str, variables: &Variables) -> Result<&Entry, FsError> {
+        let mut entry = &self.root;
+        for name in components(path) {
+            entry = match entry {
+                Entry::Directory(children, _) => children.get(name).ok_or(FsError::NotFound)?,
+                Entry::File(..) => return Err(FsError::NotADirectory),
+            };
+            if let Entry::Directory(..) = entry {
+                if !entry.is_accessible(variables) {
+                    return Err(FsError::PermissionDenied);
+                }
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Directory the player starts in
+    pub fn home(&self) -> &str {
+        &self.home
+    }
+
+    /// Absolute path of `path` typed in the directory `cwd`.
+    pub fn resolve(&self, cwd: &str, path: &str) -> String {
+        normalize(&self.home, cwd, path)
+    }
+
+    /// Names of the entries in the directory at `path`, directories ending
+    /// with a `/`.
+    pub fn list(&self, path: &str, variables: &Variables) -> Result<Vec<String>, FsError> {
+        match self.access(path, variables)? {
+            Entry::Directory(children, _) => Ok(children
+                .iter()
+                .map(|(name, entry)| match entry {
+                    Entry::Directory(..) => format!("{}/", name),
+                    Entry::File(..) => name.to_owned(),
+                })
+                .collect()),
+            Entry::File(..) => Err(FsError::NotADirectory),
+        }
+    }
+
+    /// Check that `path` is a directory the player may enter.
+    pub fn enter(&self, path: &str, variables: &Variables) -> Result<(), FsError> {
+        match self.access(path, variables)? {
+            Entry::Directory(..) => Ok(()),
+            Entry::File(..) => Err(FsError::NotADirectory),
+        }
+    }
+
+    pub fn read(&self, path: &str, variables: &Variables) -> Result<&File, FsError> {
+        let entry = self.access(path, variables)?;
+        match entry {
+            Entry::Directory(..) => Err(FsError::IsADirectory),
+            Entry::File(..) if !entry.is_accessible(variables) => Err(FsError::PermissionDenied),
+            Entry::File(file, _) => Ok(file),
+        }
+    }
+}
+
+fn parse_condition(path: &str, condition: Option<&str>) -> Result<Option<Condition>, String> {
+    condition
+        .map(Condition::parse)
+        .transpose()
+        .map_err(|err| format!("invalid condition on `{}`: {}", path, err))
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split('/').filter(|name| !name.is_empty()).collect()
+}
+
+/// Absolute path without `.`, `..` or repeated slashes.
+fn normalize(home: &str, cwd: &str, path: &str) -> String {
+    let full = if path == "~" || path.starts_with("~/") {
+        format!("{}/{}", home, &path[1..])
+    } else if path.starts_with(ROOT) {
+        path.to_string()
+    } else {
+        format!("{}/{}", cwd, path)
+    };
+
+    let mut names: Vec<&str> = Vec::new();
+    for name in components(&full) {
+        match name {
+            "." => (),
+            ".." => {
+                names.pop();
+            }
+            _ => names.push(name),
+        }
+    }
+    format!("/{}", names.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialogue::state::Value;
+
+    fn filesystem() -> FileSystem {
+        let def: FileSystemDef = toml::from_str(
+            r#"
+            home = "/home/admin"
+
+            [[directories]]
+            path = "/root"
+            condition = "is_root"
+
+            [[files]]
+            path = "/home/admin/notes.txt"
+            text = "buy milk"
+
+            [[files]]
+            path = "/home/admin/.secret"
+            text = "the password is tea"
+            condition = "!locked"
+            set = { knows_password = true }
+
+            [[files]]
+            path = "/root/keys"
+            text = "ssh keys"
+            "#,
+        )
+        .unwrap();
+        FileSystem::build(&def).unwrap()
+    }
+
+    #[test]
+    fn resolves_relative_and_home_paths() {
+        let fs = filesystem();
+
+        assert_eq!(fs.resolve("/home/admin", "notes.txt"), "/home/admin/notes.txt");
+        assert_eq!(fs.resolve("/home/admin", "../../root/./keys"), "/root/keys");
+        assert_eq!(fs.resolve("/", "~/notes.txt"), "/home/admin/notes.txt");
+        assert_eq!(fs.resolve("/root", "~"), "/home/admin");
+        assert_eq!(fs.resolve("/", "../.."), "/");
+    }
+
+    #[test]
+    fn lists_directories_with_a_slash() {
+        let fs = filesystem();
+        let vars = Variables::new();
+
+        assert_eq!(fs.list("/", &vars).unwrap(), vec!["home/", "root/"]);
+        assert_eq!(
+            fs.list("/home/admin", &vars).unwrap(),
+            vec![".secret", "notes.txt"]
+        );
+        assert_eq!(fs.list("/home/admin/notes.txt", &vars), Err(FsError::NotADirectory));
+        assert_eq!(fs.list("/tmp", &vars), Err(FsError::NotFound));
+    }
+
+    #[test]
+    fn permissions_follow_story_variables() {
+        let fs = filesystem();
+        let mut vars = Variables::new();
+        vars.insert("locked".to_string(), Value::Flag(true));
+
+        assert_eq!(fs.read("/root/keys", &vars).err(), Some(FsError::PermissionDenied));
+        assert_eq!(fs.enter("/root", &vars), Err(FsError::PermissionDenied));
+        assert_eq!(fs.read("/home/admin/.secret", &vars).err(), Some(FsError::PermissionDenied));
+
+        vars.insert("is_root".to_string(), Value::Flag(true));
+        vars.insert("locked".to_string(), Value::Flag(false));
+        assert_eq!(fs.read("/root/keys", &vars).unwrap().text, "ssh keys");
+        assert_eq!(
+            fs.read("/home/admin/.secret", &vars).unwrap().set.get("knows_password"),
+            Some(&Value::Flag(true))
+        );
+    }
+
+    #[test]
+    fn reading_a_directory_fails() {
+        let fs = filesystem();
+
+        assert_eq!(
+            fs.read("/home", &Variables::new()).err(),
+            Some(FsError::IsADirectory)
+        );
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        let def = |source: &str| toml::from_str::<FileSystemDef>(source).unwrap();
+
+        assert!(FileSystem::build(&def("[[files]]\npath = \"notes\"\ntext = \"\"")).is_err());
+        assert!(FileSystem::build(&def(
+            "[[files]]\npath = \"/a\"\ntext = \"\"\n[[files]]\npath = \"/a/b\"\ntext = \"\""
+        ))
+        .is_err());
+        assert!(FileSystem::build(&def("home = \"/nowhere\"")).is_err());
+    }
+}
@@ -1,4 +1,17 @@
+#[allow(clippy::module_inception)]
 mod dialogue;
+mod condition;
+mod conversation;
+mod filesystem;
+mod onboarding;
+mod shell;
+mod simulation;
+mod state;
+mod story;
 
+pub use conversation::{Conversation, Progress};
 pub use dialogue::Dialogue;
-pub use dialogue::CheckedDecision;
\ No newline at end of file
+pub use onboarding::{Profile, PLAYER_VARIABLE, TERMINAL_VARIABLE};
+pub use simulation::Simulation;
+pub use story::{Story, StoryFile};
+pub use state::{Value, Variables};
@@ -0,0 +1,126 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Story variable holding the name of the player
+pub static PLAYER_VARIABLE: &str = "player";
+/// Story variable holding the id of the player's terminal
+pub static TERMINAL_VARIABLE: &str = "terminal";
+
+static NAME_MIN_LEN: usize = 3;
+static NAME_MAX_LEN: usize = 16;
+
+/// Text shown when the player is asked for a username
+pub static LOGIN_PROMPT: &str = "[green]SELENIAL SECTOR[/green] remote access\n\
+    New operator detected.[pause=400] Type a username to log in.";
+
+/// Reasons a username is refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameError {
+    TooShort,
+    TooLong,
+    MustStartWithLetter,
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::TooShort => write!(f, "at least {} characters", NAME_MIN_LEN),
+            NameError::TooLong => write!(f, "at most {} characters", NAME_MAX_LEN),
+            NameError::MustStartWithLetter => write!(f, "must start with a letter"),
+            NameError::InvalidCharacter(ch) => write!(f, "'{}' is not allowed", ch),
+        }
+    }
+}
+
+/// Identity of the player, chosen when a new game starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub player: String,
+    pub terminal: String,
+}
+
+/// Check a username typed by the player.
+///
+/// Names use lowercase letters, digits, `-` and `_` and start with a
+/// letter, like a login on a real terminal.
+pub fn validate_name(name: &str) -> Result<String, NameError> {
+    let name = name.trim();
+    let len = name.chars().count();
+    if len < NAME_MIN_LEN {
+        return Err(NameError::TooShort);
+    }
+    if len > NAME_MAX_LEN {
+        return Err(NameError::TooLong);
+    }
+    if !name.starts_with(|ch: char| ch.is_ascii_lowercase()) {
+        return Err(NameError::MustStartWithLetter);
+    }
+    if let Some(ch) = name
+        .chars()
+        .find(|ch| !(ch.is_ascii_lowercase() || ch.is_ascii_digit() || *ch == '-' || *ch == '_'))
+    {
+        return Err(NameError::InvalidCharacter(ch));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Terminal id like `4821-0934` derived from `seed`.
+pub fn terminal_id(seed: u64) -> String {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    format!("{:04}-{:04}", (hash >> 32) % 10_000, hash % 10_000)
+}
+
+/// Build the profile of a player logging in as `name`.
+pub fn login(name: &str, seed: u64) -> Result<Profile, NameError> {
+    Ok(Profile {
+        player: validate_name(name)?,
+        terminal: terminal_id(seed),
+    })
+}
+
+/// Text shown after a refused username
+pub fn retry_prompt(err: NameError) -> String {
+    format!(
+        "[red]login refused:[/red] {}\nType a username to log in.",
+        err
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_terminal_like_names() {
+        assert_eq!(validate_name(" vlad_01 ").as_deref(), Ok("vlad_01"));
+        assert_eq!(validate_name("ops-team").as_deref(), Ok("ops-team"));
+    }
+
+    #[test]
+    fn refuses_invalid_names() {
+        assert_eq!(validate_name("ab"), Err(NameError::TooShort));
+        assert_eq!(validate_name(&"a".repeat(17)), Err(NameError::TooLong));
+        assert_eq!(validate_name("1337"), Err(NameError::MustStartWithLetter));
+        assert_eq!(validate_name("neo anderson"), Err(NameError::InvalidCharacter(' ')));
+        assert_eq!(validate_name("zoë"), Err(NameError::InvalidCharacter('ë')));
+    }
+
+    #[test]
+    fn terminal_ids_are_stable_for_a_seed() {
+        let id = terminal_id(42);
+
+        assert_eq!(id, terminal_id(42));
+        assert_ne!(id, terminal_id(43));
+        assert_eq!(id.len(), 9);
+        assert!(id.chars().enumerate().all(|(i, ch)| match i {
+            4 => ch == '-',
+            _ => ch.is_ascii_digit(),
+        }));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::dialogue::state::Variables;
+use crate::utils::markup;
+
+use super::{ArgSpec, Args, Command, Environment, Output};
+
+/// Every command a story can enable, in the order `help` lists them
+pub fn all() -> Vec<Box<dyn Command>> {
+    vec![
+        Box::new(Help),
+        Box::new(Clear),
+        Box::new(Whoami),
+        Box::new(Pwd),
+        Box::new(Ls),
+        Box::new(Cd),
+        Box::new(Cat),
+    ]
+}
+
+/// Names in the working directory, hidden ones left out
+fn visible_names(env: &Environment, variables: &Variables) -> Vec<String> {
+    env.fs
+        .list(&env.cwd, variables)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| !name.starts_with('.'))
+        .collect()
+}
+
+struct Help;
+
+impl Command for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn help(&self) -> &'static str {
+        "list the available commands"
+    }
+
+    fn usage(&self) -> &'static str {
+        "help"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 0, max: 0 }
+    }
+
+    fn execute(&self, _args: &Args, env: &mut Environment, _variables: &Variables) -> Output {
+        let width = env.commands.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        let lines: Vec<String> = env
+            .commands
+            .iter()
+            .map(|(name, help)| format!("{:width$}  {}", name, help, width = width))
+            .collect();
+        Output::text(lines.join("\n"))
+    }
+}
+
+struct Clear;
+
+impl Command for Clear {
+    fn name(&self) -> &'static str {
+        "clear"
+    }
+
+    fn help(&self) -> &'static str {
+        "clear the screen"
+    }
+
+    fn usage(&self) -> &'static str {
+        "clear"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 0, max: 0 }
+    }
+
+    fn execute(&self, _args: &Args, _env: &mut Environment, _variables: &Variables) -> Output {
+        Output {
+            clear: true,
+            ..Output::text(String::new())
+        }
+    }
+}
+
+struct Whoami;
+
+impl Command for Whoami {
+    fn name(&self) -> &'static str {
+        "whoami"
+    }
+
+    fn help(&self) -> &'static str {
+        "print the name of the current user"
+    }
+
+    fn usage(&self) -> &'static str {
+        "whoami"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 0, max: 0 }
+    }
+
+    fn execute(&self, _args: &Args, env: &mut Environment, _variables: &Variables) -> Output {
+        Output::text(markup::escape(&env.user))
+    }
+}
+
+struct Pwd;
+
+impl Command for Pwd {
+    fn name(&self) -> &'static str {
+        "pwd"
+    }
+
+    fn help(&self) -> &'static str {
+        "print the working directory"
+    }
+
+    fn usage(&self) -> &'static str {
+        "pwd"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 0, max: 0 }
+    }
+
+    fn execute(&self, _args: &Args, env: &mut Environment, _variables: &Variables) -> Output {
+        Output::text(markup::escape(&env.cwd))
+    }
+}
+
+struct Ls;
+
+impl Command for Ls {
+    fn name(&self) -> &'static str {
+        "ls"
+    }
+
+    fn help(&self) -> &'static str {
+        "list a directory, -a shows hidden files"
+    }
+
+    fn usage(&self) -> &'static str {
+        "ls [-a] [path]"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &["-a"], min: 0, max: 1 }
+    }
+
+    fn execute(&self, args: &Args, env: &mut Environment, variables: &Variables) -> Output {
+        let target = args.value(0).unwrap_or(".");
+        let path = env.fs.resolve(&env.cwd, target);
+
+        let text = match env.fs.list(&path, variables) {
+            Ok(names) => {
+                let shown: Vec<String> = names
+                    .into_iter()
+                    .filter(|name| args.has_flag("-a") || !name.starts_with('.'))
+                    .map(|name| markup::escape(&name))
+                    .collect();
+                shown.join("  ")
+            }
+            Err(err) => format!("ls: cannot access '{}': {}", markup::escape(target), err),
+        };
+        Output::text(text)
+    }
+
+    fn completions(&self, env: &Environment, variables: &Variables) -> Vec<String> {
+        let dirs = visible_names(env, variables)
+            .into_iter()
+            .filter_map(|name| name.strip_suffix('/').map(|dir| format!("ls {}", dir)));
+        std::iter::once(self.name().to_string()).chain(dirs).collect()
+    }
+}
+
+struct Cd;
+
+impl Command for Cd {
+    fn name(&self) -> &'static str {
+        "cd"
+    }
+
+    fn help(&self) -> &'static str {
+        "change the working directory"
+    }
+
+    fn usage(&self) -> &'static str {
+        "cd [path]"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 0, max: 1 }
+    }
+
+    fn execute(&self, args: &Args, env: &mut Environment, variables: &Variables) -> Output {
+        let target = args.value(0).unwrap_or("~");
+        let path = env.fs.resolve(&env.cwd, target);
+
+        match env.fs.enter(&path, variables) {
+            Ok(()) => {
+                env.cwd = path;
+                Output::text(String::new())
+            }
+            Err(err) => Output::text(format!("cd: {}: {}", markup::escape(target), err)),
+        }
+    }
+
+    fn completions(&self, env: &Environment, variables: &Variables) -> Vec<String> {
+        let dirs = visible_names(env, variables)
+            .into_iter()
+            .filter_map(|name| name.strip_suffix('/').map(|dir| format!("cd {}", dir)));
+        std::iter::once(self.name().to_string()).chain(dirs).collect()
+    }
+}
+
+struct Cat;
+
+impl Command for Cat {
+    fn name(&self) -> &'static str {
+        "cat"
+    }
+
+    fn help(&self) -> &'static str {
+        "print a file"
+    }
+
+    fn usage(&self) -> &'static str {
+        "cat <file>"
+    }
+
+    fn args(&self) -> ArgSpec {
+        ArgSpec { flags: &[], min: 1, max: 1 }
+    }
+
+    fn execute(&self, args: &Args, env: &mut Environment, variables: &Variables) -> Output {
+        let target = args.value(0).unwrap_or_default();
+        let path = env.fs.resolve(&env.cwd, target);
+
+        match env.fs.read(&path, variables) {
+            Ok(file) => Output {
+                set: file.set.clone(),
+                ..Output::text(file.text.to_owned())
+            },
+            Err(err) => Output::text(format!("cat: {}: {}", markup::escape(target), err)),
+        }
+    }
+
+    fn completions(&self, env: &Environment, variables: &Variables) -> Vec<String> {
+        let files = visible_names(env, variables)
+            .into_iter()
+            .filter(|name| !name.ends_with('/'))
+            .map(|name| format!("cat {}", name));
+        std::iter::once(self.name().to_string()).chain(files).collect()
+    }
+}
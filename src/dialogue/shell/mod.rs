@@ -0,0 +1,352 @@
+mod builtins;
+
+use crate::utils::markup;
+
+use super::filesystem::FileSystem;
+use super::state::Variables;
+
+/// Text printed by a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    /// Printed text, as markup
+    pub text: String,
+
+    /// Story variables set by running the command
+    pub set: Variables,
+
+    /// Whether the screen is cleared before printing
+    pub clear: bool,
+}
+
+impl Output {
+    pub fn text(text: String) -> Self {
+        Self {
+            text,
+            set: Variables::new(),
+            clear: false,
+        }
+    }
+}
+
+/// Arguments a command accepts
+#[derive(Debug, Clone, Copy)]
+pub struct ArgSpec {
+    /// Flags such as `-a`
+    pub flags: &'static [&'static str],
+
+    /// Minimum and maximum number of values that are not flags
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Arguments of a command line, checked against the command's `ArgSpec`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    pub flags: Vec<String>,
+    pub values: Vec<String>,
+}
+
+impl Args {
+    /// Split `words` into flags and values allowed by `spec`.
+    fn parse(words: &[&str], spec: &ArgSpec) -> Result<Self, String> {
+        let mut args = Self::default();
+        for word in words {
+            if word.len() > 1 && word.starts_with('-') {
+                if !spec.flags.contains(word) {
+                    return Err(format!("invalid option '{}'", word));
+                }
+                args.flags.push(word.to_string());
+            } else {
+                args.values.push(word.to_string());
+            }
+        }
+
+        if args.values.len() < spec.min {
+            return Err("missing operand".to_string());
+        }
+        if args.values.len() > spec.max {
+            return Err("too many arguments".to_string());
+        }
+        Ok(args)
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    pub fn value(&self, index: usize) -> Option<&str> {
+        self.values.get(index).map(String::as_str)
+    }
+}
+
+/// State shared by the commands of a shell
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub fs: FileSystem,
+
+    /// Absolute path of the working directory
+    pub cwd: String,
+
+    /// Name of the player logged in the terminal
+    pub user: String,
+
+    /// Name and help text of the enabled commands
+    pub commands: Vec<(&'static str, &'static str)>,
+}
+
+/// Command the player can run at the prompt
+///
+/// Commands are registered in `builtins::all` and the story file may
+/// enable only some of them with its `commands` list.
+pub trait Command {
+    /// Word typed to run the command
+    fn name(&self) -> &'static str;
+
+    /// One line description shown by `help`
+    fn help(&self) -> &'static str;
+
+    /// Arguments written like `ls [-a] [path]`, shown on invalid arguments
+    fn usage(&self) -> &'static str;
+
+    fn args(&self) -> ArgSpec;
+
+    fn execute(&self, args: &Args, env: &mut Environment, variables: &Variables) -> Output;
+
+    /// Lines completing the command with its arguments
+    fn completions(&self, _env: &Environment, _variables: &Variables) -> Vec<String> {
+        vec![self.name().to_string()]
+    }
+}
+
+/// Runs the registered commands typed at the prompt
+pub struct Shell {
+    commands: Vec<Box<dyn Command>>,
+    env: Environment,
+}
+
+impl Shell {
+    /// Shell browsing `fs`, with only the `enabled` commands when given.
+    pub fn new(fs: FileSystem, enabled: Option<&[String]>) -> Self {
+        let commands: Vec<Box<dyn Command>> = builtins::all()
+            .into_iter()
+            .filter(|command| enabled.is_none_or(|names| names.iter().any(|n| n == command.name())))
+            .collect();
+
+        let env = Environment {
+            cwd: fs.home().to_string(),
+            fs,
+            user: String::new(),
+            commands: commands.iter().map(|c| (c.name(), c.help())).collect(),
+        };
+        Self { commands, env }
+    }
+
+    pub fn set_user(&mut self, user: &str) {
+        self.env.user = user.to_string();
+    }
+
+    /// Absolute path of the working directory
+    pub fn cwd(&self) -> &str {
+        &self.env.cwd
+    }
+
+    /// Move to the directory `path`, as restored from a save.
+    ///
+    /// Returns `false` and stays put when the directory is gone or the
+    /// player may not enter it.
+    pub fn set_cwd(&mut self, path: &str, variables: &Variables) -> bool {
+        let entered = self.env.fs.enter(path, variables).is_ok();
+        if entered {
+            self.env.cwd = path.to_string();
+        }
+        entered
+    }
+
+    /// Run `line` if it starts with the name of an enabled command.
+    ///
+    /// Returns `None` when the line is not a shell command so it can be
+    /// handled as a story command instead.
+    pub fn execute(&mut self, line: &str, variables: &Variables) -> Option<Output> {
+        let mut words = line.split_whitespace();
+        let name = words.next()?;
+        let words: Vec<&str> = words.collect();
+        let command = self.commands.iter().find(|c| c.name() == name)?;
+
+        let output = match Args::parse(&words, &command.args()) {
+            Ok(args) => command.execute(&args, &mut self.env, variables),
+            Err(err) => Output::text(format!(
+                "{}: {}\nusage: {}",
+                name,
+                markup::escape(&err),
+                command.usage()
+            )),
+        };
+        Some(output)
+    }
+
+    /// Lines the player may complete at the prompt
+    pub fn completions(&self, variables: &Variables) -> Vec<String> {
+        self.commands
+            .iter()
+            .flat_map(|command| command.completions(&self.env, variables))
+            .collect()
+    }
+}
+
+/// Whether a command with this name can be enabled by a story
+pub fn is_command(name: &str) -> bool {
+    builtins::all().iter().any(|command| command.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialogue::filesystem::FileSystemDef;
+    use crate::dialogue::state::Value;
+
+    fn filesystem() -> FileSystem {
+        let def: FileSystemDef = toml::from_str(
+            r#"
+            home = "/home/admin"
+
+            [[files]]
+            path = "/home/admin/notes.txt"
+            text = "buy [b]milk[/b]"
+
+            [[files]]
+            path = "/home/admin/.recipe"
+            text = "boil water"
+            set = { knows_recipe = true }
+
+            [[files]]
+            path = "/var/log/boot"
+            text = "ok"
+            "#,
+        )
+        .unwrap();
+        FileSystem::build(&def).unwrap()
+    }
+
+    fn shell() -> Shell {
+        let mut shell = Shell::new(filesystem(), None);
+        shell.set_user("vlad");
+        shell
+    }
+
+    fn run(shell: &mut Shell, line: &str) -> String {
+        shell.execute(line, &Variables::new()).unwrap().text
+    }
+
+    #[test]
+    fn restores_existing_working_directories() {
+        let mut shell = shell();
+        let variables = Variables::new();
+
+        assert!(shell.set_cwd("/var/log", &variables));
+        assert_eq!(shell.cwd(), "/var/log");
+        assert!(!shell.set_cwd("/var/gone", &variables));
+        assert!(!shell.set_cwd("/var/log/boot", &variables));
+        assert_eq!(shell.cwd(), "/var/log");
+    }
+
+    #[test]
+    fn ls_hides_dot_files_unless_asked() {
+        let mut shell = shell();
+
+        assert_eq!(run(&mut shell, "ls"), "notes.txt");
+        assert_eq!(run(&mut shell, "ls -a"), ".recipe  notes.txt");
+        assert_eq!(run(&mut shell, "ls /"), "home/  var/");
+    }
+
+    #[test]
+    fn cd_changes_the_working_directory() {
+        let mut shell = shell();
+
+        assert_eq!(run(&mut shell, "cd /var/log"), "");
+        assert_eq!(run(&mut shell, "pwd"), "/var/log");
+        assert_eq!(run(&mut shell, "cat boot"), "ok");
+
+        run(&mut shell, "cd");
+        assert_eq!(run(&mut shell, "pwd"), "/home/admin");
+        assert_eq!(
+            run(&mut shell, "cd notes.txt"),
+            "cd: notes.txt: Not a directory"
+        );
+    }
+
+    #[test]
+    fn cat_prints_files_and_triggers_variables() {
+        let mut shell = shell();
+
+        assert_eq!(run(&mut shell, "cat notes.txt"), "buy [b]milk[/b]");
+        assert_eq!(
+            run(&mut shell, "cat [x]"),
+            "cat: [[x]: No such file or directory"
+        );
+
+        let output = shell.execute("cat .recipe", &Variables::new()).unwrap();
+        assert_eq!(output.set.get("knows_recipe"), Some(&Value::Flag(true)));
+    }
+
+    #[test]
+    fn invalid_arguments_print_the_usage() {
+        let mut shell = shell();
+
+        assert_eq!(
+            run(&mut shell, "cat"),
+            "cat: missing operand\nusage: cat <file>"
+        );
+        assert_eq!(
+            run(&mut shell, "ls -l"),
+            "ls: invalid option '-l'\nusage: ls [-a] [path]"
+        );
+        assert_eq!(
+            run(&mut shell, "whoami now"),
+            "whoami: too many arguments\nusage: whoami"
+        );
+    }
+
+    #[test]
+    fn help_whoami_and_clear() {
+        let mut shell = shell();
+
+        let help = run(&mut shell, "help");
+        assert!(help.lines().any(|line| line.starts_with("cat ")));
+        assert!(help.lines().any(|line| line.starts_with("whoami ")));
+        assert_eq!(run(&mut shell, "whoami"), "vlad");
+        assert!(shell.execute("clear", &Variables::new()).unwrap().clear);
+    }
+
+    #[test]
+    fn stories_can_enable_some_commands() {
+        let enabled = vec!["help".to_string(), "cat".to_string()];
+        let mut shell = Shell::new(filesystem(), Some(&enabled));
+
+        assert_eq!(shell.execute("ls", &Variables::new()), None);
+        assert_eq!(run(&mut shell, "cat notes.txt"), "buy [b]milk[/b]");
+        assert_eq!(run(&mut shell, "help").lines().count(), 2);
+
+        assert!(is_command("whoami"));
+        assert!(!is_command("drink"));
+    }
+
+    #[test]
+    fn other_lines_are_not_commands() {
+        let mut shell = shell();
+
+        assert_eq!(shell.execute("drink", &Variables::new()), None);
+        assert_eq!(shell.execute("   ", &Variables::new()), None);
+    }
+
+    #[test]
+    fn completes_names_in_the_working_directory() {
+        let mut shell = shell();
+        run(&mut shell, "cd /");
+
+        let lines = shell.completions(&Variables::new());
+        assert!(lines.contains(&"help".to_string()));
+        assert!(lines.contains(&"cd var".to_string()));
+        assert!(lines.contains(&"ls home".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("notes.txt")));
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use crate::utils::layout::MonoMeasure;
+
+use super::state::Variables;
+use super::{Conversation, Progress, StoryFile};
+
+/// Time advanced between two reveal steps, matching a 60 FPS frame
+static FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// Frames after which a dialogue that is still revealing is skipped
+static MAX_FRAMES_PER_DIALOGUE: usize = 100_000;
+
+/// Result of a scripted playthrough
+#[derive(Debug, Clone)]
+pub struct Playthrough {
+    /// Every dialogue line shown, with submitted commands as `> command`
+    pub transcript: Vec<String>,
+
+    /// Id of the dialogue with no decisions left, if the story reached one
+    pub ending: Option<String>,
+
+    /// Commands that did not match any available decision or shell command
+    pub rejected: Vec<String>,
+
+    /// Story variables at the end of the playthrough
+    pub variables: Variables,
+}
+
+/// Plays a story without a window
+///
+/// Scripted commands are fed to a `Conversation` exactly like the ones typed
+/// at the prompt, and dialogue text is revealed frame by frame with a fixed
+/// frame time and a monospace layout.
+pub struct Simulation {
+    conversation: Conversation,
+    measure: MonoMeasure,
+}
+
+impl Simulation {
+    /// Simulate `story` on a screen `columns` characters wide.
+    pub fn new(story: &StoryFile, columns: usize, text_speed: f32) -> Self {
+        let measure = MonoMeasure { advance: 1.0 };
+        let story = story.build(columns as f32 * measure.advance, text_speed);
+
+        Self {
+            conversation: Conversation::new(story),
+            measure,
+        }
+    }
+
+    /// Ask for a username before the story starts, the first commands of
+    /// the script being the names typed.
+    pub fn onboarding(mut self, seed: u64) -> Self {
+        self.conversation.start_onboarding(seed);
+        self
+    }
+
+    /// Feed the commands of `script` one by one until the story ends.
+    pub fn run<'a, I>(mut self, script: I) -> Playthrough
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut transcript = Vec::new();
+        let mut rejected = Vec::new();
+        let mut ending = None;
+
+        self.reveal(&mut transcript);
+        for command in script {
+            if self.conversation.is_ending() {
+                ending = Some(self.conversation.current_id());
+                break;
+            }
+
+            transcript.push(format!("> {}", command));
+            match self.conversation.submit(command) {
+                Progress::Idle => rejected.push(command.to_string()),
+                Progress::Advanced | Progress::Printed | Progress::Cleared => {
+                    self.reveal(&mut transcript)
+                }
+                Progress::Ended => break,
+            }
+        }
+
+        if ending.is_none() && self.conversation.is_ending() {
+            ending = Some(self.conversation.current_id());
+        }
+
+        Playthrough {
+            transcript,
+            ending,
+            rejected,
+            variables: self.conversation.variables().clone(),
+        }
+    }
+
+    /// Reveal the current dialogue and add its lines to the transcript.
+    fn reveal(&mut self, transcript: &mut Vec<String>) {
+        let mut frames = 0;
+        while !self.conversation.is_revealed() {
+            if frames >= MAX_FRAMES_PER_DIALOGUE {
+                self.conversation.skip(&self.measure);
+                break;
+            }
+            self.conversation.update(FRAME_TIME, &self.measure);
+            frames += 1;
+        }
+
+        transcript.extend(self.conversation.lines().iter().map(|line| line.text()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialogue::state::Value;
+
+    static CHAPTER_1: &str = include_str!("../../static/stories/chapter1.toml");
+
+    fn chapter_1() -> StoryFile {
+        StoryFile::parse(CHAPTER_1).expect("chapter 1 story is valid")
+    }
+
+    #[test]
+    fn milk_path_reaches_drink_ending() {
+        let playthrough = Simulation::new(&chapter_1(), 80, 40.0).run(["milk", "drink"]);
+
+        assert_eq!(playthrough.ending.as_deref(), Some("drink"));
+        assert_eq!(
+            playthrough.transcript,
+            vec![
+                "What do you choose ? Milk or Tea ?",
+                "> milk",
+                "Nice you chose the only one we have",
+                "> drink",
+                "You drink the milk. It is still warm.",
+            ]
+        );
+        assert!(playthrough.rejected.is_empty());
+    }
+
+    #[test]
+    fn complaining_requires_asking_for_tea() {
+        let milk = Simulation::new(&chapter_1(), 80, 0.0).run(["milk", "complain"]);
+        assert_eq!(milk.rejected, vec!["complain"]);
+        assert_eq!(milk.ending, None);
+
+        let tea = Simulation::new(&chapter_1(), 80, 0.0).run(["tea", "complain"]);
+        assert_eq!(tea.ending.as_deref(), Some("complain"));
+        assert_eq!(tea.variables.get("asked_for_tea"), Some(&Value::Flag(true)));
+        assert_eq!(
+            tea.transcript.last().map(String::as_str),
+            Some("Complaints about the tea go to the admin. You are the admin.")
+        );
+    }
+
+    #[test]
+    fn commands_are_case_and_space_insensitive() {
+        let playthrough = Simulation::new(&chapter_1(), 80, 0.0).run(["  MILK ", "Drink"]);
+
+        assert_eq!(playthrough.ending.as_deref(), Some("drink"));
+    }
+
+    #[test]
+    fn reading_the_recipe_unlocks_brewing() {
+        let playthrough = Simulation::new(&chapter_1(), 80, 40.0).run([
+            "milk",
+            "brew tea",
+            "ls",
+            "ls -a",
+            "cat /root/stock.log",
+            "cat .recipe",
+            "brew tea",
+        ]);
+
+        assert_eq!(playthrough.rejected, vec!["brew tea"]);
+        assert_eq!(playthrough.ending.as_deref(), Some("brew"));
+        assert_eq!(
+            playthrough.transcript[4..],
+            [
+                "> ls",
+                "todo.txt",
+                "> ls -a",
+                ".recipe  todo.txt",
+                "> cat /root/stock.log",
+                "cat: /root/stock.log: Permission denied",
+                "> cat .recipe",
+                "GRANDMA'S TEA",
+                "Boil the water, wait three minutes, brew tea.",
+                "> brew tea",
+                "You follow the recipe and brew a cup of tea. It tastes like victory, admin.",
+            ]
+        );
+    }
+
+    #[test]
+    fn new_players_log_in_before_the_story() {
+        let playthrough = Simulation::new(&chapter_1(), 80, 0.0)
+            .onboarding(7)
+            .run(["ab", "neo", "whoami", "milk"]);
+
+        assert_eq!(
+            playthrough.transcript,
+            vec![
+                "SELENIAL SECTOR remote access",
+                "New operator detected. Type a username to log in.",
+                "> ab",
+                "login refused: at least 3 characters",
+                "Type a username to log in.",
+                "> neo",
+                "What do you choose ? Milk or Tea ?",
+                "> whoami",
+                "neo",
+                "> milk",
+                "Nice you chose the only one we have",
+            ]
+        );
+        assert_eq!(playthrough.variables.get("player"), Some(&Value::Text("neo".to_string())));
+        assert!(matches!(playthrough.variables.get("terminal"), Some(Value::Text(_))));
+    }
+
+    #[test]
+    fn story_can_start_from_another_node() {
+        let mut story = chapter_1();
+        story.set_start("tea").unwrap();
+        assert!(story.set_start("coffee").is_err());
+
+        let playthrough = Simulation::new(&story, 80, 0.0).run(["complain"]);
+        assert_eq!(playthrough.ending.as_deref(), Some("complain"));
+    }
+
+    #[test]
+    fn text_wraps_to_the_screen_width() {
+        let story = StoryFile::parse(
+            r#"
+            start = "intro"
+
+            [[nodes]]
+            id = "intro"
+            text = "connecting to the [red]remote[/red] host\nsupercalifragilistic 你好世界"
+            "#,
+        )
+        .unwrap();
+        let playthrough = Simulation::new(&story, 12, 30.0).run(Vec::new());
+
+        assert_eq!(
+            playthrough.transcript,
+            vec![
+                "connecting ",
+                "to the ",
+                "remote host",
+                "supercalifr-",
+                "agilistic 你",
+                "好世界",
+            ]
+        );
+        assert_eq!(playthrough.ending.as_deref(), Some("intro"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::markup;
+
+/// Value of a story variable
+///
+/// Written in story files as plain TOML values: `true`, `3` or `"milk"`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Flag(bool),
+    Int(i64),
+    Text(String),
+}
+
+impl Value {
+    /// Whether the value counts as set when used alone in a condition
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Flag(flag) => *flag,
+            Value::Int(number) => *number != 0,
+            Value::Text(text) => !text.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Flag(flag) => write!(f, "{}", flag),
+            Value::Int(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Story variables by name
+pub type Variables = BTreeMap<String, Value>;
+
+/// Replace `{name}` in `text` with the value of the variable `name`.
+///
+/// Unknown variables are left as written and `{{` writes a literal `{`.
+/// Values are escaped so they are never read as markup.
+pub fn interpolate(text: &str, variables: &Variables) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("{{") {
+            result.push('{');
+            rest = &rest[2..];
+            continue;
+        }
+
+        let value = rest
+            .find('}')
+            .and_then(|end| variables.get(&rest[1..end]).map(|value| (end, value)));
+        match value {
+            Some((end, value)) => {
+                result.push_str(&markup::escape(&value.to_string()));
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_known_variables() {
+        let mut variables = Variables::new();
+        variables.insert("player".to_string(), Value::Text("vlad".to_string()));
+        variables.insert("cups".to_string(), Value::Int(2));
+        variables.insert("host".to_string(), Value::Text("[lab]".to_string()));
+
+        assert_eq!(
+            interpolate("Welcome back, {player}. {cups} cups left.", &variables),
+            "Welcome back, vlad. 2 cups left."
+        );
+        assert_eq!(interpolate("{host}", &variables), "[[lab]");
+        assert_eq!(interpolate("{unknown} {{player} {", &variables), "{unknown} {player} {");
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::{cell::RefCell, rc::Rc};
+
+use ggez::{GameError, GameResult};
+use serde::Deserialize;
+
+use crate::audio::Sound;
+use crate::utils::{TextBox, TextMeasure};
+
+use super::condition::Condition;
+use super::dialogue::normalize_command;
+use super::filesystem::{FileSystem, FileSystemDef};
+use super::shell;
+use super::state::Variables;
+use super::Dialogue;
+
+/// Errors raised while reading or validating a story file
+#[derive(Debug, PartialEq)]
+pub enum StoryError {
+    /// The file is not valid TOML or does not match the story format
+    Parse(String),
+
+    /// Two nodes share the same id
+    DuplicateId(String),
+
+    /// A node or the story start points to an id that does not exist
+    DanglingReference { from: String, to: String },
+
+    /// A node can be reached from a parent but has no command to select it
+    MissingCommand { from: String, to: String },
+
+    /// Two decisions of the same node are selected by the same command
+    AmbiguousCommand { node: String, command: String },
+
+    /// A node condition could not be parsed
+    InvalidCondition { node: String, message: String },
+
+    /// The virtual filesystem has an invalid path or condition
+    InvalidFileSystem(String),
+
+    /// The story enables a shell command that does not exist
+    UnknownCommand(String),
+
+    /// A node plays a stinger that does not exist
+    UnknownSound { node: String, sound: String },
+}
+
+impl fmt::Display for StoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoryError::Parse(msg) => write!(f, "invalid story file: {}", msg),
+            StoryError::DuplicateId(id) => write!(f, "duplicate node id `{}`", id),
+            StoryError::DanglingReference { from, to } => {
+                write!(f, "`{}` references unknown node `{}`", from, to)
+            }
+            StoryError::MissingCommand { from, to } => {
+                write!(f, "`{}` leads to `{}` which has no command", from, to)
+            }
+            StoryError::AmbiguousCommand { node, command } => {
+                write!(f, "node `{}` has several decisions for `{}`", node, command)
+            }
+            StoryError::InvalidCondition { node, message } => {
+                write!(f, "invalid condition on node `{}`: {}", node, message)
+            }
+            StoryError::InvalidFileSystem(msg) => write!(f, "invalid filesystem: {}", msg),
+            StoryError::UnknownCommand(name) => write!(f, "unknown shell command `{}`", name),
+            StoryError::UnknownSound { node, sound } => write!(
+                f,
+                "node `{}` plays unknown sound `{}`, available sounds: {}",
+                node,
+                sound,
+                Sound::stinger_names().join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StoryError {}
+
+impl From<StoryError> for GameError {
+    fn from(err: StoryError) -> Self {
+        GameError::ResourceLoadError(err.to_string())
+    }
+}
+
+/// A single dialogue node as written in the story file
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeDef {
+    pub id: String,
+    pub text: String,
+
+    /// Command typed at the prompt that selects this node from its parent
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Condition over story variables that must hold for this node to be
+    /// offered as a decision
+    #[serde(default)]
+    pub condition: Option<String>,
+
+    /// Story variables set when the conversation reaches this node
+    #[serde(default)]
+    pub set: Variables,
+
+    /// Characters per second revealed for this node, overriding the
+    /// global text speed
+    #[serde(default)]
+    pub speed: Option<f32>,
+
+    /// Stinger played when the conversation reaches this node
+    #[serde(default)]
+    pub sound: Option<String>,
+
+    /// Ids of the nodes the player can move to from this node
+    #[serde(default)]
+    pub decisions: Vec<String>,
+}
+
+/// Story file describing a dialogue graph
+///
+/// Stories are written in TOML under `static/stories` and list every
+/// dialogue node together with the edges between them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoryFile {
+    /// Version of the story content, bumped when nodes are renamed or
+    /// removed so older saves can be detected
+    #[serde(default)]
+    pub version: u32,
+
+    /// Id of the node the conversation starts from
+    pub start: String,
+
+    /// Initial value of the story variables
+    #[serde(default)]
+    pub variables: Variables,
+
+    pub nodes: Vec<NodeDef>,
+
+    /// Files the player can browse from the prompt
+    #[serde(default)]
+    pub filesystem: FileSystemDef,
+
+    /// Shell commands enabled in this story, all of them when not set
+    pub commands: Option<Vec<String>>,
+}
+
+impl StoryFile {
+    /// Load a story file from disk.
+    pub fn load(path: &Path) -> GameResult<Self> {
+        let source = fs::read_to_string(path)?;
+
+        Self::parse(&source).map_err(|err| {
+            GameError::ResourceLoadError(format!("{}: {}", path.display(), err))
+        })
+    }
+
+    /// Parse and validate a story from its TOML source.
+    pub fn parse(source: &str) -> Result<Self, StoryError> {
+        let story: StoryFile = toml::from_str(source)
+            .map_err(|err| StoryError::Parse(err.to_string()))?;
+        story.validate()?;
+
+        Ok(story)
+    }
+
+    /// Start the conversation from the node `id` instead.
+    pub fn set_start(&mut self, id: &str) -> Result<(), StoryError> {
+        if !self.nodes.iter().any(|node| node.id == id) {
+            return Err(StoryError::DanglingReference {
+                from: "start".to_string(),
+                to: id.to_string(),
+            });
+        }
+
+        self.start = id.to_string();
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), StoryError> {
+        let mut ids = HashSet::new();
+        for node in self.nodes.iter() {
+            if !ids.insert(node.id.as_str()) {
+                return Err(StoryError::DuplicateId(node.id.to_owned()));
+            }
+        }
+
+        if !ids.contains(self.start.as_str()) {
+            return Err(StoryError::DanglingReference {
+                from: "start".to_string(),
+                to: self.start.to_owned(),
+            });
+        }
+
+        for node in self.nodes.iter() {
+            if let Some(sound) = &node.sound {
+                if Sound::stinger(sound).is_none() {
+                    return Err(StoryError::UnknownSound {
+                        node: node.id.to_owned(),
+                        sound: sound.to_owned(),
+                    });
+                }
+            }
+            if let Some(condition) = &node.condition {
+                Condition::parse(condition).map_err(|err| StoryError::InvalidCondition {
+                    node: node.id.to_owned(),
+                    message: err.to_string(),
+                })?;
+            }
+        }
+
+        let conditional: HashSet<&str> = self
+            .nodes
+            .iter()
+            .filter(|node| node.condition.is_some())
+            .map(|node| node.id.as_str())
+            .collect();
+
+        let commands: HashMap<&str, Option<String>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let command = node.command.as_deref().map(normalize_command);
+                (node.id.as_str(), command.filter(|c| !c.is_empty()))
+            })
+            .collect();
+
+        for node in self.nodes.iter() {
+            let mut seen = HashSet::new();
+            for target in node.decisions.iter() {
+                let command = match commands.get(target.as_str()) {
+                    Some(Some(command)) => command,
+                    Some(None) => {
+                        return Err(StoryError::MissingCommand {
+                            from: node.id.to_owned(),
+                            to: target.to_owned(),
+                        })
+                    }
+                    None => {
+                        return Err(StoryError::DanglingReference {
+                            from: node.id.to_owned(),
+                            to: target.to_owned(),
+                        })
+                    }
+                };
+
+                // Decisions guarded by a condition may share a command, the
+                // condition decides which one is offered
+                if !conditional.contains(target.as_str()) && !seen.insert(command) {
+                    return Err(StoryError::AmbiguousCommand {
+                        node: node.id.to_owned(),
+                        command: command.to_owned(),
+                    });
+                }
+            }
+        }
+
+        FileSystem::build(&self.filesystem).map_err(StoryError::InvalidFileSystem)?;
+
+        for name in self.commands.iter().flatten() {
+            if !shell::is_command(name) {
+                return Err(StoryError::UnknownCommand(name.to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the dialogue graph described by the file.
+    ///
+    /// Nodes are created first and wired afterwards so decisions may point
+    /// to any node in the file, including earlier ones.
+    pub fn build(&self, width: f32, text_speed: f32) -> Story {
+        let mut nodes = HashMap::new();
+        for def in self.nodes.iter() {
+            let mut dialogue = Dialogue::new(
+                def.id.to_owned(),
+                def.text.to_owned(),
+                None,
+                def.command.to_owned(),
+                width,
+                def.speed.unwrap_or(text_speed),
+            );
+            dialogue.set_condition(def.condition.as_deref().and_then(|c| Condition::parse(c).ok()));
+            dialogue.set_on_enter(def.set.clone());
+            dialogue.set_sound(def.sound.clone());
+            nodes.insert(def.id.to_owned(), Rc::new(RefCell::new(dialogue)));
+        }
+
+        for def in self.nodes.iter() {
+            if def.decisions.is_empty() {
+                continue;
+            }
+
+            let decisions = def
+                .decisions
+                .iter()
+                .map(|target| Rc::clone(&nodes[target]))
+                .collect();
+            nodes[&def.id].borrow_mut().set_decisions(Some(decisions));
+        }
+
+        Story {
+            version: self.version,
+            variables: self.variables.clone(),
+            start: self.start.to_owned(),
+            nodes,
+            filesystem: FileSystem::build(&self.filesystem).unwrap_or_default(),
+            commands: self.commands.clone(),
+            width,
+            text_speed,
+        }
+    }
+}
+
+/// Dialogue graph built from a `StoryFile`
+pub struct Story {
+    version: u32,
+    variables: Variables,
+    start: String,
+    nodes: HashMap<String, Rc<RefCell<Dialogue>>>,
+    filesystem: FileSystem,
+    commands: Option<Vec<String>>,
+
+    /// Width and speed the dialogue text is revealed with
+    width: f32,
+    text_speed: f32,
+}
+
+impl Story {
+    /// Dialogue the conversation starts from
+    pub fn start(&self) -> Rc<RefCell<Dialogue>> {
+        Rc::clone(&self.nodes[&self.start])
+    }
+
+    /// Dialogue with the given id, if the story has one
+    pub fn get(&self, id: &str) -> Option<Rc<RefCell<Dialogue>>> {
+        self.nodes.get(id).map(Rc::clone)
+    }
+
+    /// Story variables before the conversation starts
+    pub fn initial_variables(&self) -> Variables {
+        self.variables.clone()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Virtual filesystem the player starts browsing from
+    pub fn filesystem(&self) -> FileSystem {
+        self.filesystem.clone()
+    }
+
+    /// Shell commands enabled in this story, all of them when `None`
+    pub fn commands(&self) -> Option<&[String]> {
+        self.commands.as_deref()
+    }
+
+    /// Wrap the text of every dialogue to a new `width`.
+    pub fn resize(&mut self, width: f32, measure: &dyn TextMeasure) {
+        self.width = width;
+        for node in self.nodes.values() {
+            node.borrow_mut().resize(width, measure);
+        }
+    }
+
+    /// Textbox revealing `text` like the dialogue text
+    pub fn text_box(&self, text: String) -> TextBox {
+        TextBox::new(self.width, text, self.text_speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Story starting at `start` with the given nodes, written as TOML
+    fn story(nodes: &str) -> String {
+        format!("start = \"start\"\n{}", nodes)
+    }
+
+    fn error(nodes: &str) -> StoryError {
+        StoryFile::parse(&story(nodes)).unwrap_err()
+    }
+
+    #[test]
+    fn accepts_a_valid_story() {
+        let source = story(
+            r#"
+            [[nodes]]
+            id = "start"
+            text = "Milk or tea?"
+            decisions = ["milk", "tea"]
+
+            [[nodes]]
+            id = "milk"
+            text = "Milk it is."
+            command = "milk"
+
+            [[nodes]]
+            id = "tea"
+            text = "Tea it is."
+            command = "Tea"
+            "#,
+        );
+        assert!(StoryFile::parse(&source).is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let err = error(
+            r#"
+            [[nodes]]
+            id = "start"
+            text = "first"
+
+            [[nodes]]
+            id = "start"
+            text = "second"
+            "#,
+        );
+        assert_eq!(err, StoryError::DuplicateId("start".to_string()));
+    }
+
+    #[test]
+    fn rejects_dangling_references() {
+        let err = error(
+            r#"
+            [[nodes]]
+            id = "intro"
+            text = "no start"
+            "#,
+        );
+        assert_eq!(
+            err,
+            StoryError::DanglingReference { from: "start".to_string(), to: "start".to_string() }
+        );
+
+        let err = error(
+            r#"
+            [[nodes]]
+            id = "start"
+            text = "where to?"
+            decisions = ["nowhere"]
+            "#,
+        );
+        assert_eq!(
+            err,
+            StoryError::DanglingReference { from: "start".to_string(), to: "nowhere".to_string() }
+        );
+    }
+
+    #[test]
+    fn rejects_decisions_without_commands() {
+        let err = error(
+            r#"
+            [[nodes]]
+            id = "start"
+            text = "where to?"
+            decisions = ["silent"]
+
+            [[nodes]]
+            id = "silent"
+            text = "no command"
+            command = "  "
+            "#,
+        );
+        assert_eq!(
+            err,
+            StoryError::MissingCommand { from: "start".to_string(), to: "silent".to_string() }
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_commands_unless_conditional() {
+        let nodes = |condition: &str| {
+            format!(
+                r#"
+                [[nodes]]
+                id = "start"
+                text = "drink?"
+                decisions = ["milk", "more_milk"]
+
+                [[nodes]]
+                id = "milk"
+                text = "Milk."
+                command = "drink milk"
+
+                [[nodes]]
+                id = "more_milk"
+                text = "More milk."
+                command = "Drink  Milk"
+                {}
+                "#,
+                condition
+            )
+        };
+
+        assert_eq!(
+            error(&nodes("")),
+            StoryError::AmbiguousCommand {
+                node: "start".to_string(),
+                command: "drink milk".to_string(),
+            }
+        );
+        assert!(StoryFile::parse(&story(&nodes("condition = \"cups > 1\""))).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_sounds() {
+        let nodes = |sound: &str| {
+            format!(
+                r#"
+                [[nodes]]
+                id = "start"
+                text = "connected"
+                sound = "{}"
+                "#,
+                sound
+            )
+        };
+
+        assert!(StoryFile::parse(&story(&nodes("connect"))).is_ok());
+        assert_eq!(
+            error(&nodes("fanfare")),
+            StoryError::UnknownSound { node: "start".to_string(), sound: "fanfare".to_string() }
+        );
+    }
+}
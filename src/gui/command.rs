@@ -1,10 +1,18 @@
-use ggez::event::KeyCode;
-use ggez::graphics::{Text, Font, self, Color};
+use std::time::Duration;
+
+use ggez::event::{KeyCode, KeyMods};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, PxScale, Rect, Text, TextFragment};
 use ggez::{Context, GameResult};
 
-use crate::utils::Point2D;
+use crate::dialogue::Profile;
+use crate::theme::Theme;
+use crate::utils::{Completer, Completion, LineEditor, Point2D};
 
-static FONT_PATH: &str = "/fonts/RobotoMono-VariableFont_wght.ttf";
+static CARET_WIDTH: f32 = 2.0;
+/// Time the caret stays visible, then hidden, while blinking
+static CARET_BLINK: Duration = Duration::from_millis(530);
+/// Terminal info shown until the player has logged in
+static GUEST_INFO: &str = "$ ";
 
 
 // pub enum TerminalOp {
@@ -16,59 +24,176 @@ static FONT_PATH: &str = "/fonts/RobotoMono-VariableFont_wght.ttf";
 // }
 
 pub struct TerminalInput {
-    text: String,
+    editor: LineEditor,
+    completer: Completer,
     font: Font,
-    pub current_command: Option<KeyCode>
+    font_size: f32,
+
+    /// Player and terminal shown before the typed line
+    info: String,
+
+    /// Colours of the terminal info and of the typed line
+    prompt_color: Color,
+    input_color: Color,
+
+    /// Top left corner of the prompt
+    pos: Point2D,
+
+    /// Time since the caret was last moved, drives the blinking
+    blink: Duration,
 }
 impl TerminalInput {
-    pub fn new(ctx: &mut Context) -> GameResult<Self> {
-        Ok(Self {
-            text: String::new(),
-            font: Font::new(ctx, FONT_PATH)?,
-            current_command: None
-        })
+    pub fn new(font: Font, theme: &Theme) -> Self {
+        Self {
+            editor: LineEditor::new(),
+            completer: Completer::new(),
+            font,
+            font_size: theme.font_size,
+            info: GUEST_INFO.to_string(),
+            prompt_color: theme.prompt,
+            input_color: theme.input,
+            pos: Point2D::new(0.0, 0.0),
+            blink: Duration::ZERO,
+        }
     }
 
-    pub fn _add(&mut self, value: String) {
-        self.text.push_str(&value.to_lowercase())
+    /// Move the prompt, following the layout of the window.
+    pub fn set_position(&mut self, pos: Point2D) {
+        self.pos = pos;
     }
 
-    pub fn _reset(&mut self) {
-        self.text.clear()
+    /// Show the player and terminal of `profile` before the typed line.
+    pub fn set_profile(&mut self, profile: &Profile) {
+        self.info = format!("<{}@{}> $ ", profile.player, profile.terminal);
     }
 
-    pub fn _backspace(&mut self) {
-        if self.text.len() > 0 {
-            self.text.pop().unwrap();
-        }
+    pub fn add(&mut self, value: &str) {
+        let lowercase: String = value.chars().flat_map(char::to_lowercase).collect();
+        self.editor.insert(&lowercase);
     }
 
-    fn build(&self, ctx: &mut Context) -> GameResult {
-        let pos = Point2D::new(130.0, 530.0);
-        let mut user_input_body = self.text.to_owned();
-        user_input_body.push_str("_");
-        let text = Text::new((user_input_body.to_owned(), self.font, 18.0));
+    /// Whether nothing is typed at the prompt
+    pub fn is_empty(&self) -> bool {
+        self.editor.text().is_empty()
+    }
+
+    /// Take the typed line out of the prompt, leaving it empty.
+    ///
+    /// Returns `None` when nothing but whitespace was typed.
+    pub fn submit(&mut self) -> Option<String> {
+        self.keep_caret();
+        self.completer.reset();
+        self.editor.submit()
+    }
 
-        graphics::draw(ctx, &text, (pos.as_vec(), ))
+    fn fragment(&self, text: String) -> TextFragment {
+        TextFragment::new(text)
+            .font(self.font)
+            .scale(PxScale::from(self.font_size))
+            .color(self.input_color)
     }
 
-    fn draw_terminal_info(&self, ctx: &mut Context) -> GameResult {
-        let pos = Point2D::new(20.0, 530.0);
+    fn build(&self, ctx: &mut Context, info_width: f32) -> GameResult {
+        let pos = self.pos + Point2D::new(info_width, 0.0);
+        let text = Text::new(self.fragment(self.editor.text()));
+        graphics::draw(ctx, &text, (pos.as_vec(), ))?;
+
+        if self.blink.as_millis() / CARET_BLINK.as_millis() % 2 == 1 {
+            return Ok(());
+        }
+
+        let caret_x = Text::new(self.fragment(self.editor.before_caret())).width(ctx);
+        let caret = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(pos.x + caret_x, pos.y, CARET_WIDTH, self.font_size),
+            self.input_color,
+        )?;
+        graphics::draw(ctx, &caret, DrawParam::default())
+    }
 
-        let term_info_body = String::from("<admin-001> $ ");
-        let term_info_text = Text::new((term_info_body.to_owned(), self.font, 18.0));
+    /// Draw the terminal info before the typed text, returns its width.
+    fn draw_terminal_info(&self, ctx: &mut Context) -> GameResult<f32> {
+        let term_info_text = Text::new((self.info.as_str(), self.font, self.font_size));
 
-        graphics::draw(ctx, &term_info_text, (pos.as_vec(), Color::MAGENTA))
+        graphics::draw(ctx, &term_info_text, (self.pos.as_vec(), self.prompt_color))?;
+        Ok(term_info_text.width(ctx))
     }
 
     pub fn display(&self, ctx: &mut Context) -> GameResult {
-        self.build(ctx)?;
-        self.draw_terminal_info(ctx)?;
+        let info_width = self.draw_terminal_info(ctx)?;
+        self.build(ctx, info_width)?;
         Ok(())
     }
 
-    pub fn update(&mut self) {
-        ()
+    /// Keep the caret visible while the player is typing.
+    fn keep_caret(&mut self) {
+        self.blink = Duration::ZERO;
+    }
+
+    /// Blink the caret for the `elapsed` time.
+    pub fn tick(&mut self, elapsed: Duration) {
+        self.blink += elapsed;
     }
 
-}
\ No newline at end of file
+    /// Insert typed text at the caret.
+    pub fn type_text(&mut self, text: &str) {
+        self.keep_caret();
+        self.completer.reset();
+        self.add(text);
+    }
+
+    /// Complete the text before the caret with the `candidates` lines.
+    ///
+    /// Returns the matches to list when several candidates are left.
+    pub fn complete(&mut self, candidates: &[String]) -> Option<Vec<String>> {
+        self.keep_caret();
+        match self.completer.complete(&self.editor.before_caret(), candidates) {
+            Completion::None => None,
+            Completion::Replace(line) => {
+                self.editor.replace_before_caret(&line);
+                None
+            }
+            Completion::Ambiguous { line, matches } => {
+                self.editor.replace_before_caret(&line);
+                Some(matches)
+            }
+        }
+    }
+
+    /// Delete the character before the caret, for the virtual keyboard.
+    pub fn backspace(&mut self) {
+        self.press(KeyCode::Back, KeyMods::NONE);
+    }
+
+    /// Apply an editing key: moving the caret, deleting, browsing the
+    /// history, and the shell shortcuts held with `Ctrl`.
+    ///
+    /// Returns whether the key edits the line, others are ignored.
+    pub fn press(&mut self, key: KeyCode, mods: KeyMods) -> bool {
+        let edit: fn(&mut LineEditor) = match (mods.contains(KeyMods::CTRL), key) {
+            (true, KeyCode::A) => LineEditor::home,
+            (true, KeyCode::E) => LineEditor::end,
+            (true, KeyCode::W) => LineEditor::kill_word,
+            (true, KeyCode::U) => LineEditor::kill_to_start,
+            (true, KeyCode::K) => LineEditor::kill_to_end,
+            (true, KeyCode::Y) => LineEditor::yank,
+            (true, _) => return false,
+            (false, KeyCode::Back) => LineEditor::backspace,
+            (false, KeyCode::Delete) => LineEditor::delete,
+            (false, KeyCode::Left) => LineEditor::left,
+            (false, KeyCode::Right) => LineEditor::right,
+            (false, KeyCode::Home) => LineEditor::home,
+            (false, KeyCode::End) => LineEditor::end,
+            (false, KeyCode::Up) => LineEditor::history_up,
+            (false, KeyCode::Down) => LineEditor::history_down,
+            (false, _) => return false,
+        };
+
+        self.keep_caret();
+        self.completer.reset();
+        edit(&mut self.editor);
+        true
+    }
+
+}
@@ -0,0 +1,141 @@
+use gfx::{self, *};
+use ggez::graphics::{self, Canvas, Color, DrawParam, Shader};
+use ggez::{timer, Context, GameResult};
+
+use crate::settings::CrtSettings;
+
+static VERTEX_SHADER: &str = "/shaders/crt_150.glslv";
+static PIXEL_SHADER: &str = "/shaders/crt_150.glslf";
+/// Name of the uniform block in the pixel shader
+static UNIFORMS_NAME: &str = "Crt";
+
+gfx_defines! {
+    constant CrtUniforms {
+        width: f32 = "u_Width",
+        height: f32 = "u_Height",
+        time: f32 = "u_Time",
+        scanlines: f32 = "u_Scanlines",
+        curvature: f32 = "u_Curvature",
+        bloom: f32 = "u_Bloom",
+        aberration: f32 = "u_Aberration",
+        flicker: f32 = "u_Flicker",
+    }
+}
+
+impl CrtUniforms {
+    /// Values sent to the shader for a frame of `width` by `height`
+    /// pixels, `time` seconds after the start.
+    fn new(settings: &CrtSettings, width: f32, height: f32, time: f32) -> Self {
+        Self {
+            width,
+            height,
+            time,
+            scanlines: settings.scanlines.clamp(0.0, 1.0),
+            curvature: settings.curvature.max(0.0),
+            bloom: settings.bloom.max(0.0),
+            aberration: settings.aberration.max(0.0),
+            flicker: settings.flicker.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Post-processing pass making the frame look like a CRT monitor
+///
+/// The GUI is drawn into an offscreen canvas between `begin` and `finish`,
+/// which then draws the canvas to the window through the CRT shader.
+pub struct Crt {
+    settings: CrtSettings,
+    shader: Shader<CrtUniforms>,
+    canvas: Canvas,
+}
+
+impl Crt {
+    /// Load the shader when the pass is enabled in the settings.
+    ///
+    /// Returns `None` when it is disabled or the shader cannot be used, in
+    /// which case the frame is drawn as is.
+    pub fn load(ctx: &mut Context, settings: &CrtSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        match Self::new(ctx, settings) {
+            Ok(crt) => Some(crt),
+            Err(err) => {
+                eprintln!("CRT effect disabled, the shader is unavailable: {}", err);
+                None
+            }
+        }
+    }
+
+    fn new(ctx: &mut Context, settings: &CrtSettings) -> GameResult<Self> {
+        let (width, height) = graphics::drawable_size(ctx);
+        let uniforms = CrtUniforms::new(settings, width, height, 0.0);
+        let shader = Shader::new(ctx, VERTEX_SHADER, PIXEL_SHADER, uniforms, UNIFORMS_NAME, None)?;
+
+        Ok(Self {
+            settings: *settings,
+            shader,
+            canvas: Canvas::with_window_size(ctx)?,
+        })
+    }
+
+    /// Match the canvas to the new drawable size.
+    pub fn resize(&mut self, ctx: &mut Context) -> GameResult {
+        self.canvas = Canvas::with_window_size(ctx)?;
+        Ok(())
+    }
+
+    /// Run `draw` on the offscreen canvas cleared with `background`, then
+    /// draw the canvas to the window through the shader.
+    ///
+    /// The window is drawn to again even when `draw` fails, otherwise
+    /// every later frame would stay on the canvas.
+    pub fn render<F>(&self, ctx: &mut Context, background: Color, draw: F) -> GameResult
+    where
+        F: FnOnce(&mut Context) -> GameResult,
+    {
+        graphics::set_canvas(ctx, Some(&self.canvas));
+        graphics::clear(ctx, background);
+        let result = draw(ctx);
+        graphics::set_canvas(ctx, None);
+        result?;
+        self.finish(ctx)
+    }
+
+    /// Draw the canvas to the window through the shader.
+    fn finish(&self, ctx: &mut Context) -> GameResult {
+
+        let (width, height) = graphics::drawable_size(ctx);
+        let time = timer::time_since_start(ctx).as_secs_f32();
+        let uniforms = CrtUniforms::new(&self.settings, width, height, time);
+
+        let _lock = graphics::use_shader(ctx, &self.shader);
+        self.shader.send(ctx, uniforms)?;
+        graphics::draw(ctx, &self.canvas, DrawParam::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strengths_stay_in_range() {
+        let settings = CrtSettings {
+            enabled: true,
+            scanlines: 3.0,
+            curvature: -1.0,
+            bloom: 0.4,
+            aberration: -2.0,
+            flicker: 1.5,
+        };
+        let uniforms = CrtUniforms::new(&settings, 800.0, 600.0, 1.0);
+
+        assert_eq!(uniforms.scanlines, 1.0);
+        assert_eq!(uniforms.curvature, 0.0);
+        assert_eq!(uniforms.bloom, 0.4);
+        assert_eq!(uniforms.aberration, 0.0);
+        assert_eq!(uniforms.flicker, 1.0);
+    }
+}
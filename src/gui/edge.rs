@@ -1,45 +1,40 @@
-use ggez::graphics::{self, Color, DrawParam, Mesh, MeshBuilder};
+use ggez::graphics::{self, DrawParam, Mesh, MeshBuilder, Rect};
 use ggez::{Context, GameResult};
 
+use crate::theme::Theme;
 use crate::utils::Point2D;
- 
-pub type Margins = (Point2D, Point2D);
-
-pub trait Vector {
-    fn get_start(&self) -> &Point2D;
-    fn get_end(&self) -> &Point2D;
-}
 
 /// Represents an edge of the gui screen
 #[derive(Clone, Debug)]
 pub struct Edge {
-    margins: Margins,
     mesh: Mesh,
 }
 
 impl Edge {
-    pub fn new(x: Point2D, y: Point2D, ctx: &mut Context) -> GameResult<Edge> {
+    pub fn new(x: Point2D, y: Point2D, theme: &Theme, ctx: &mut Context) -> GameResult<Edge> {
         let line = MeshBuilder::new()
-            .line(&[x.as_vec(), y.as_vec()], 2.0, Color::WHITE)?
+            .line(&[x.as_vec(), y.as_vec()], theme.border_width, theme.border)?
             .build(ctx)?;
-        Ok(Self {
-            mesh: line,
-            margins: (x, y)
-        })
+        Ok(Self { mesh: line })
     }
 
-    pub fn display(&self, ctx: &mut Context) -> GameResult {
-        let draw_params = DrawParam::new();
-        graphics::draw(ctx, &self.mesh, draw_params)
-    }
-}
+    /// Upper, lower, left and right edges of `rect`
+    pub fn around(rect: Rect, theme: &Theme, ctx: &mut Context) -> GameResult<Vec<Edge>> {
+        let top_left = Point2D::new(rect.left(), rect.top());
+        let top_right = Point2D::new(rect.right(), rect.top());
+        let bottom_left = Point2D::new(rect.left(), rect.bottom());
+        let bottom_right = Point2D::new(rect.right(), rect.bottom());
 
-impl Vector for Edge {
-    fn get_start(&self) -> &Point2D {
-        &self.margins.0
+        Ok(vec![
+            Edge::new(top_left, top_right, theme, ctx)?,
+            Edge::new(bottom_left, bottom_right, theme, ctx)?,
+            Edge::new(top_left, bottom_left, theme, ctx)?,
+            Edge::new(top_right, bottom_right, theme, ctx)?,
+        ])
     }
 
-    fn get_end(&self) -> &Point2D {
-        &self.margins.1
+    pub fn display(&self, ctx: &mut Context) -> GameResult {
+        let draw_params = DrawParam::new();
+        graphics::draw(ctx, &self.mesh, draw_params)
     }
 }
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use ggez::graphics::Rect;
+
+/// Space kept empty around a panel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Margins {
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    /// Same margin on every side
+    pub fn all(margin: f32) -> Self {
+        Self::new(margin, margin, margin, margin)
+    }
+
+    fn shrink(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + self.left,
+            rect.y + self.top,
+            (rect.w - self.left - self.right).max(0.0),
+            (rect.h - self.top - self.bottom).max(0.0),
+        )
+    }
+}
+
+/// Extent of a panel along the direction its parent is split in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// Size in pixels
+    Fixed(f32),
+
+    /// Fraction of the parent's size
+    Ratio(f32),
+
+    /// Share of what the other panels leave, split evenly
+    Fill,
+}
+
+/// Direction the children of a panel are stacked in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// Top to bottom
+    Rows,
+
+    /// Left to right
+    Columns,
+}
+
+/// Box of the window layout, possibly split into nested panels
+///
+/// Panels with a name become a region of the `Frame` the interface draws
+/// in, and panels with a border get lines drawn around them.
+#[derive(Debug, Clone)]
+pub struct Panel {
+    name: Option<&'static str>,
+    size: Size,
+    margins: Margins,
+    border: bool,
+    split: Split,
+    children: Vec<Panel>,
+}
+
+impl Panel {
+    /// Panel filling its parent
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            size: Size::Fill,
+            margins: Margins::default(),
+            border: false,
+            split: Split::Rows,
+            children: Vec::new(),
+        }
+    }
+
+    /// Region drawn in by a part of the interface
+    pub fn named(name: &'static str) -> Self {
+        Self {
+            name: Some(name),
+            ..Self::new()
+        }
+    }
+
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn margins(mut self, margins: Margins) -> Self {
+        self.margins = margins;
+        self
+    }
+
+    /// Draw lines around the panel, inside its margins
+    pub fn border(mut self) -> Self {
+        self.border = true;
+        self
+    }
+
+    /// Stack `children` from top to bottom
+    pub fn rows(mut self, children: Vec<Panel>) -> Self {
+        self.split = Split::Rows;
+        self.children = children;
+        self
+    }
+
+    /// Stack `children` from left to right
+    pub fn columns(mut self, children: Vec<Panel>) -> Self {
+        self.split = Split::Columns;
+        self.children = children;
+        self
+    }
+
+    /// Place the panel and its children in `area`.
+    pub fn layout(&self, area: Rect) -> Frame {
+        let mut frame = Frame::default();
+        self.place(area, &mut frame);
+        frame
+    }
+
+    fn place(&self, area: Rect, frame: &mut Frame) {
+        let inner = self.margins.shrink(area);
+        if let Some(name) = self.name {
+            frame.regions.insert(name, inner);
+        }
+        if self.border {
+            frame.borders.push(inner);
+        }
+
+        let length = match self.split {
+            Split::Rows => inner.h,
+            Split::Columns => inner.w,
+        };
+        let fills = self.children.iter().filter(|c| c.size == Size::Fill).count();
+        let taken: f32 = self
+            .children
+            .iter()
+            .map(|child| match child.size {
+                Size::Fixed(size) => size,
+                Size::Ratio(ratio) => ratio * length,
+                Size::Fill => 0.0,
+            })
+            .sum();
+        let fill = (length - taken).max(0.0) / fills.max(1) as f32;
+
+        let mut offset = 0.0;
+        for child in self.children.iter() {
+            let size = match child.size {
+                Size::Fixed(size) => size,
+                Size::Ratio(ratio) => ratio * length,
+                Size::Fill => fill,
+            };
+            let slot = match self.split {
+                Split::Rows => Rect::new(inner.x, inner.y + offset, inner.w, size),
+                Split::Columns => Rect::new(inner.x + offset, inner.y, size, inner.h),
+            };
+            child.place(slot, frame);
+            offset += size;
+        }
+    }
+}
+
+/// Regions and borders of a laid out `Panel`
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    regions: HashMap<&'static str, Rect>,
+    borders: Vec<Rect>,
+}
+
+impl Frame {
+    /// Area of the panel called `name`, empty when there is none
+    pub fn region(&self, name: &str) -> Rect {
+        self.regions
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, 0.0, 0.0))
+    }
+
+    /// Rectangles to draw a border around
+    pub fn borders(&self) -> &[Rect] {
+        &self.borders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_panels_leave_the_rest_to_fill() {
+        let panel = Panel::new().margins(Margins::new(0.0, 20.0, 0.0, 20.0)).rows(vec![
+            Panel::named("top").size(Size::Fixed(40.0)),
+            Panel::named("middle").border(),
+            Panel::named("bottom")
+                .size(Size::Fixed(100.0))
+                .margins(Margins::new(30.0, 0.0, 0.0, 0.0)),
+        ]);
+        let frame = panel.layout(Rect::new(0.0, 0.0, 1000.0, 600.0));
+
+        assert_eq!(frame.region("top"), Rect::new(20.0, 0.0, 960.0, 40.0));
+        assert_eq!(frame.region("middle"), Rect::new(20.0, 40.0, 960.0, 460.0));
+        assert_eq!(frame.region("bottom"), Rect::new(20.0, 530.0, 960.0, 70.0));
+        assert_eq!(frame.borders(), &[Rect::new(20.0, 40.0, 960.0, 460.0)]);
+    }
+
+    #[test]
+    fn nested_panels_split_their_parent() {
+        let panel = Panel::new().columns(vec![
+            Panel::named("screen"),
+            Panel::new().size(Size::Ratio(0.25)).rows(vec![
+                Panel::named("map").margins(Margins::all(5.0)),
+                Panel::named("inventory"),
+            ]),
+        ]);
+        let frame = panel.layout(Rect::new(0.0, 0.0, 800.0, 400.0));
+
+        assert_eq!(frame.region("screen"), Rect::new(0.0, 0.0, 600.0, 400.0));
+        assert_eq!(frame.region("map"), Rect::new(605.0, 5.0, 190.0, 190.0));
+        assert_eq!(frame.region("inventory"), Rect::new(600.0, 200.0, 200.0, 200.0));
+        assert_eq!(frame.region("missing"), Rect::new(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn panels_never_get_a_negative_size() {
+        let panel = Panel::new().rows(vec![
+            Panel::named("header").size(Size::Fixed(300.0)),
+            Panel::named("body").margins(Margins::all(50.0)),
+        ]);
+        let frame = panel.layout(Rect::new(0.0, 0.0, 200.0, 200.0));
+
+        assert_eq!(frame.region("body").h, 0.0);
+    }
+}
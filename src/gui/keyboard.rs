@@ -0,0 +1,202 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::theme::Theme;
+use crate::utils::Point2D;
+
+/// Character keys of the keyboard, one string per row
+static ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl-", "zxcvbnm./_"];
+/// Keys wider than a character, on the last row
+static SPECIAL_KEYS: [VirtualKey; 3] = [VirtualKey::Space, VirtualKey::Back, VirtualKey::Enter];
+/// Height of a key relative to the font size
+static KEY_HEIGHT: f32 = 1.8;
+/// Space around the keys and between them
+static KEY_SPACING: f32 = 4.0;
+
+/// Key of the on-screen keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualKey {
+    Char(char),
+    Space,
+    Back,
+    Enter,
+}
+
+impl VirtualKey {
+    fn label(&self) -> String {
+        match self {
+            VirtualKey::Char(ch) => ch.to_string(),
+            VirtualKey::Space => "space".to_string(),
+            VirtualKey::Back => "del".to_string(),
+            VirtualKey::Enter => "enter".to_string(),
+        }
+    }
+}
+
+/// Keys of the keyboard and the highlighted one
+struct KeyGrid {
+    rows: Vec<Vec<VirtualKey>>,
+    row: usize,
+    column: usize,
+}
+
+impl KeyGrid {
+    fn new() -> Self {
+        let mut rows: Vec<Vec<VirtualKey>> = ROWS
+            .iter()
+            .map(|row| row.chars().map(VirtualKey::Char).collect())
+            .collect();
+        rows.push(SPECIAL_KEYS.to_vec());
+
+        Self {
+            rows,
+            row: 0,
+            column: 0,
+        }
+    }
+
+    /// Move the highlight by `columns` and `rows`, wrapping around the
+    /// edges of the keyboard.
+    fn move_cursor(&mut self, columns: isize, rows: isize) {
+        self.row = wrap(self.row, rows, self.rows.len());
+        // Rows have different lengths, keep the column on the new one
+        let length = self.rows[self.row].len();
+        self.column = wrap(self.column.min(length - 1), columns, length);
+    }
+
+    fn selected(&self) -> VirtualKey {
+        self.rows[self.row][self.column]
+    }
+}
+
+/// On-screen keyboard typing at the prompt with a gamepad
+///
+/// The D-pad moves the highlight between keys, which are pressed by the
+/// GUI. Drawn over the bottom of its area while open.
+pub struct VirtualKeyboard {
+    grid: KeyGrid,
+    is_open: bool,
+
+    /// Area the keyboard is drawn at the bottom of
+    area: Rect,
+
+    font: Font,
+    font_size: f32,
+    text_color: Color,
+    highlight_color: Color,
+    background: Color,
+    border_color: Color,
+}
+
+impl VirtualKeyboard {
+    pub fn new(font: Font, theme: &Theme) -> Self {
+        Self {
+            grid: KeyGrid::new(),
+            is_open: false,
+            area: Rect::new(0.0, 0.0, 0.0, 0.0),
+            font,
+            font_size: theme.font_size,
+            text_color: theme.text,
+            highlight_color: theme.input,
+            background: theme.background,
+            border_color: theme.border,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn open(&mut self) {
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    /// Move the highlight by `columns` and `rows`, see `KeyGrid`.
+    pub fn move_cursor(&mut self, columns: isize, rows: isize) {
+        self.grid.move_cursor(columns, rows);
+    }
+
+    /// Key under the highlight
+    pub fn selected(&self) -> VirtualKey {
+        self.grid.selected()
+    }
+
+    pub fn display(&self, ctx: &mut Context) -> GameResult {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        let key_height = self.font_size * KEY_HEIGHT;
+        let rows = &self.grid.rows;
+        let height = rows.len() as f32 * (key_height + KEY_SPACING) + KEY_SPACING;
+        let panel = Rect::new(
+            self.area.x,
+            self.area.y + (self.area.h - height).max(0.0),
+            self.area.w,
+            height,
+        );
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), panel, self.background)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        let border = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), panel, self.border_color)?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        for (row, keys) in rows.iter().enumerate() {
+            let key_width = (panel.w - KEY_SPACING) / keys.len() as f32 - KEY_SPACING;
+            let y = panel.y + KEY_SPACING + row as f32 * (key_height + KEY_SPACING);
+            for (column, key) in keys.iter().enumerate() {
+                let x = panel.x + KEY_SPACING + column as f32 * (key_width + KEY_SPACING);
+                let rect = Rect::new(x, y, key_width, key_height);
+                let selected = (row, column) == (self.grid.row, self.grid.column);
+                self.draw_key(ctx, key, rect, selected)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a key with its label centred, filled when highlighted.
+    fn draw_key(&self, ctx: &mut Context, key: &VirtualKey, rect: Rect, selected: bool) -> GameResult {
+        let (mode, label_color) = match selected {
+            true => (DrawMode::fill(), self.background),
+            false => (DrawMode::stroke(1.0), self.text_color),
+        };
+        let outline = Mesh::new_rectangle(ctx, mode, rect, self.highlight_color)?;
+        graphics::draw(ctx, &outline, DrawParam::default())?;
+
+        let label = Text::new((key.label(), self.font, self.font_size));
+        let (width, height) = (label.width(ctx), label.height(ctx));
+        let pos = Point2D::new(rect.x + (rect.w - width) / 2.0, rect.y + (rect.h - height) / 2.0);
+        graphics::draw(ctx, &label, (pos.as_vec(), label_color))
+    }
+}
+
+/// Move `index` by `step` in `0..length`, wrapping around
+fn wrap(index: usize, step: isize, length: usize) -> usize {
+    (index as isize + step).rem_euclid(length as isize) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_wraps_and_stays_on_shorter_rows() {
+        let mut grid = KeyGrid::new();
+
+        grid.move_cursor(-1, 0);
+        assert_eq!(grid.selected(), VirtualKey::Char('0'));
+        grid.move_cursor(0, -1);
+        assert_eq!(grid.selected(), VirtualKey::Enter);
+        grid.move_cursor(1, 0);
+        assert_eq!(grid.selected(), VirtualKey::Space);
+        grid.move_cursor(0, 1);
+        assert_eq!(grid.selected(), VirtualKey::Char('1'));
+    }
+}
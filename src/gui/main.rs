@@ -1,55 +1,464 @@
-use ggez::event;
-use ggez::graphics::{self, Font};
-use ggez::{Context, GameResult};
-use ndarray::{arr3, s, Array3, Axis};
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::event::{self, KeyCode};
+use ggez::graphics::{self, Font, Rect};
+use ggez::{timer, Context, GameResult};
 
 use crate::utils::Point2D;
 
 use super::command::TerminalInput;
-use super::stats::{GUIStats, PlayerName, TerminalName};
+use super::crt::Crt;
+use super::stats::{Chapter, Clock, Connection, GUIStats, PlayerName, TerminalName, Trace};
 use super::edge::Edge;
+use super::frame::{Frame, Margins, Panel, Size};
+use super::keyboard::{VirtualKey, VirtualKeyboard};
+use super::options::OptionsScreen;
 use super::screen::Screen;
 
+use crate::audio::{Audio, Sound};
+use crate::input::{Action, InputEvent, Keymap, SavedKeys};
+use crate::dialogue::{Profile, Progress, StoryFile};
+use crate::save::{SaveData, SaveSlots, SAVE_VERSION};
+use crate::settings::GameSettings;
+use crate::theme::Theme;
+
 static DEFAULT_MARGIN: f32 = 20.0;
+/// Space between the screen border and its text
+static SCREEN_PADDING: f32 = 10.0;
 static LOWER_MARGIN: f32 = 100.0;
-static FONT_PATH: &str = "/fonts/RobotoMono-VariableFont_wght.ttf";
+/// Distance from the top of the window to the stats
+static STATS_OFFSET: f32 = 15.0;
+/// Distance from the lower edge to the prompt
+static INPUT_OFFSET: f32 = 30.0;
+
+static STATS_REGION: &str = "stats";
+static SCREEN_REGION: &str = "screen";
+static INPUT_REGION: &str = "input";
+
+/// Line opening the options screen from the prompt
+static OPTIONS_COMMAND: &str = "options";
+
+/// Layout of the window: the stats, the bordered screen and the prompt
+/// under it
+fn terminal_layout() -> Panel {
+    Panel::new()
+        .margins(Margins::new(0.0, DEFAULT_MARGIN, 0.0, DEFAULT_MARGIN))
+        .rows(vec![
+            Panel::named(STATS_REGION)
+                .size(Size::Fixed(DEFAULT_MARGIN * 2.0))
+                .margins(Margins::new(STATS_OFFSET, 0.0, 0.0, 0.0)),
+            Panel::new().border().rows(vec![
+                Panel::named(SCREEN_REGION).margins(Margins::all(SCREEN_PADDING)),
+            ]),
+            Panel::named(INPUT_REGION)
+                .size(Size::Fixed(LOWER_MARGIN))
+                .margins(Margins::new(INPUT_OFFSET, 0.0, 0.0, 0.0)),
+        ])
+}
 
 pub struct GUI {
-    edges: Box<Vec<Edge>>,
+    theme: Theme,
+    layout: Panel,
+    /// Post-processing pass, `None` draws straight to the window
+    crt: Option<Crt>,
+    edges: Vec<Edge>,
     stats: GUIStats,
     term_input: TerminalInput,
+    keyboard: VirtualKeyboard,
+    options: OptionsScreen,
     screen: Screen,
+    audio: Audio,
+    saves: SaveSlots,
+    save_slot: u32,
+
+    /// A decision was picked with the last choice key, the character it
+    /// types is dropped when it arrives
+    chose_by_key: bool,
 }
 
 impl GUI {
-    pub fn new(ctx: &mut Context) -> GameResult<Self> {
-        // TODO: Change these
-        let username = PlayerName::new("Vlad".to_string());
-        let term_name = TerminalName::new("0001-0001".to_string());
-        let term_input = TerminalInput::new(ctx)?;
-        let edges = Box::new(Self::build_edges(ctx)?);
-        let font = Font::new(ctx, FONT_PATH)?;
-        let screen = Screen::new(edges.clone(), font);
+    /// Build the interface playing `story`, resuming the progress of the
+    /// save slot when `resume` is set.
+    ///
+    /// Without a save the player is first asked for a username. Sounds
+    /// are only played when `audio` is available.
+    pub fn new(
+        ctx: &mut Context,
+        settings: &GameSettings,
+        theme: &Theme,
+        story: &StoryFile,
+        resume: bool,
+        audio: bool,
+    ) -> GameResult<Self> {
+        let saves = SaveSlots::new(ctx);
+        let save = match resume {
+            true => saves.load(settings.save_slot)?,
+            false => None,
+        };
+
+        let layout = terminal_layout();
+        let frame = Self::place(&layout, ctx);
+
+        let font = Font::new(ctx, &theme.font)?;
+        let mut term_input = TerminalInput::new(font, theme);
+        term_input.set_position(Self::input_position(&frame));
+        let mut keyboard = VirtualKeyboard::new(font, theme);
+        keyboard.set_area(frame.region(SCREEN_REGION));
+        let mut options = Self::build_options(ctx, settings, font, theme)?;
+        options.set_area(frame.region(SCREEN_REGION));
+        let edges = Self::build_edges(&frame, theme, ctx)?;
+        let mut screen = Screen::new(frame.region(SCREEN_REGION), font, theme, story, settings);
+
+        let clock = save.as_ref().and_then(|data| data.clock);
+        match save {
+            Some(data) => Self::restore(&mut screen, data),
+            None => screen.start_onboarding(Self::seed()),
+        }
+
+        if let Some(profile) = screen.profile() {
+            term_input.set_profile(&profile);
+        }
+
+        let mut stats = GUIStats::new(
+            vec![
+                PlayerName::new(),
+                TerminalName::new(),
+                Chapter::new(),
+                Connection::new(),
+                Trace::new(),
+                Clock::new(),
+            ],
+            font,
+            theme,
+        );
+        stats.set_area(ctx, frame.region(STATS_REGION));
+        if let Some(minutes) = clock {
+            stats.set_time(ctx, minutes);
+        }
 
         Ok(Self {
+            theme: theme.clone(),
+            layout,
+            crt: Crt::load(ctx, &settings.crt),
             edges,
-            stats: GUIStats::new(vec![username, term_name], ctx)?,
+            stats,
             term_input,
+            keyboard,
+            options,
             screen,
+            audio: Audio::new(ctx, &settings.audio, audio),
+            saves,
+            save_slot: settings.save_slot,
+            chose_by_key: false,
         })
     }
 
-    pub fn update(&mut self, ctx: &mut Context, new_input: Option<event::KeyCode>) -> GameResult {
-        // Update terminal input
-        self.term_input.update();
+    /// Options screen editing the bindings of the settings, with the keys
+    /// rebound in game applied over them.
+    fn build_options(
+        ctx: &Context,
+        settings: &GameSettings,
+        font: Font,
+        theme: &Theme,
+    ) -> GameResult<OptionsScreen> {
+        let defaults = Keymap::new(&settings.keys, &settings.buttons)?;
+        let saved = SavedKeys::new(ctx);
+        let keymap = match saved.load() {
+            Ok(Some(keys)) => defaults.with_keys(&keys).unwrap_or_else(|err| {
+                eprintln!("Ignoring the rebound keys: {}", err);
+                defaults.clone()
+            }),
+            Ok(None) => defaults.clone(),
+            Err(err) => {
+                eprintln!("Could not load the rebound keys: {}", err);
+                defaults.clone()
+            }
+        };
+        Ok(OptionsScreen::new(font, theme, defaults, keymap, saved))
+    }
+
+    /// Lay the interface out again for the current drawable size.
+    pub fn resize(&mut self, ctx: &mut Context) -> GameResult {
+        let frame = Self::place(&self.layout, ctx);
+        self.edges = Self::build_edges(&frame, &self.theme, ctx)?;
+        self.screen.resize(ctx, frame.region(SCREEN_REGION));
+        self.keyboard.set_area(frame.region(SCREEN_REGION));
+        self.options.set_area(frame.region(SCREEN_REGION));
+        self.stats.set_area(ctx, frame.region(STATS_REGION));
+        self.term_input.set_position(Self::input_position(&frame));
+        if let Some(crt) = self.crt.as_mut() {
+            crt.resize(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Regions of `layout` over the whole window
+    fn place(layout: &Panel, ctx: &Context) -> Frame {
+        let (width, height) = graphics::drawable_size(ctx);
+        layout.layout(Rect::new(0.0, 0.0, width, height))
+    }
+
+    fn input_position(frame: &Frame) -> Point2D {
+        let area = frame.region(INPUT_REGION);
+        Point2D::new(area.x, area.y)
+    }
+
+    /// Write the current progress to the active save slot.
+    ///
+    /// Nothing is saved until the player has chosen a username.
+    pub fn save(&self) -> GameResult {
+        let profile = match self.screen.profile() {
+            Some(profile) if !self.screen.is_onboarding() => profile,
+            _ => return Ok(()),
+        };
+        let (node, history, variables) = self.screen.snapshot();
+        let data = SaveData {
+            version: SAVE_VERSION,
+            story_version: self.screen.story_version(),
+            node,
+            history,
+            player_name: profile.player,
+            terminal_name: profile.terminal,
+            variables,
+            cwd: Some(self.screen.cwd().to_string()),
+            clock: self.stats.time(),
+        };
+
+        self.saves.save(self.save_slot, &data)
+    }
+
+    /// Seed of the terminal id given to a new player
+    fn seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default()
+    }
+
+    /// Continue from `data`, telling the player on the screen when the
+    /// save was made with another version of the story.
+    fn restore(screen: &mut Screen, mut data: SaveData) {
+        if data.story_version != screen.story_version() {
+            eprintln!(
+                "Save was made with story version {}, current version is {}",
+                data.story_version,
+                screen.story_version()
+            );
+            data.history.push(format!(
+                "[yellow]-- save from story version {}, now {} --[/yellow]",
+                data.story_version,
+                screen.story_version()
+            ));
+        }
+
+        if !screen.restore(&data.node, data.history, data.variables, data.cwd.as_deref()) {
+            eprintln!(
+                "Saved dialogue `{}` no longer exists, starting the story over",
+                data.node
+            );
+        }
+        screen.set_profile(&Profile {
+            player: data.player_name,
+            terminal: data.terminal_name,
+        });
+    }
+
+    /// Apply the input of the frame, then reveal the text and update the
+    /// stats for the time elapsed.
+    pub fn update(&mut self, ctx: &mut Context, events: &[InputEvent]) -> GameResult {
+        let elapsed = timer::delta(ctx);
+
+        for event in events.iter() {
+            match self.options.is_open() {
+                true => self.handle_options(ctx, event),
+                false => self.handle(ctx, event)?,
+            }
+        }
+
+        // Update terminal screen and the stats following it
+        self.term_input.tick(elapsed);
+        self.screen.update(ctx, elapsed);
+        if self.screen.revealed() > 0 {
+            self.audio.play(ctx, Sound::Click);
+        }
+        self.stats.update(ctx, elapsed, self.screen.variables());
+
+        Ok(())
+    }
+
+    fn handle(&mut self, ctx: &mut Context, event: &InputEvent) -> GameResult {
+        match *event {
+            InputEvent::Action(action) => return self.act(ctx, action),
+            InputEvent::ActionText(_) if mem::take(&mut self.chose_by_key) => (),
+            InputEvent::Text(character) | InputEvent::ActionText(character) => {
+                self.audio.play(ctx, Sound::Key);
+                self.term_input.type_text(&character.to_string());
+            }
+            // Typed characters come as text as well, only edits click here
+            InputEvent::Key(key, mods) => {
+                if self.term_input.press(key, mods) {
+                    self.audio.play(ctx, Sound::Key);
+                }
+            }
+            InputEvent::MouseMove(pos) => self.screen.hover(pos),
+            InputEvent::Click(pos) => {
+                let choice = self.screen.choice_at(pos).and_then(|i| self.screen.choose(i));
+                if let Some(command) = choice {
+                    return self.submit(ctx, &command);
+                }
+            }
+            InputEvent::Wheel(lines) => self.screen.scroll_wheel(lines),
+        }
+        Ok(())
+    }
 
-        // Update terminal screen
-        self.screen.update(ctx, new_input)?;
+    /// Apply an action.
+    ///
+    /// While the virtual keyboard is open the directions move between its
+    /// keys, `Confirm` presses one and `Skip` deletes a character.
+    /// Otherwise they highlight and pick a decision.
+    fn act(&mut self, ctx: &mut Context, action: Action) -> GameResult {
+        let keyboard = self.keyboard.is_open();
+        match action {
+            Action::Submit => {
+                self.audio.play(ctx, Sound::Enter);
+                match self.term_input.submit() {
+                    Some(line) if line.trim() == OPTIONS_COMMAND => self.options.open(),
+                    Some(line) => return self.submit(ctx, &line),
+                    // An empty line leaves the story once it has ended
+                    None if self.screen.is_ending() => return self.submit(ctx, ""),
+                    None => (),
+                }
+            }
+            Action::Complete => {
+                let mut candidates = self.screen.completions();
+                candidates.push(OPTIONS_COMMAND.to_string());
+                if let Some(matches) = self.term_input.complete(&candidates) {
+                    self.screen.list_matches(ctx, &matches);
+                }
+            }
+            Action::Skip if keyboard => self.term_input.backspace(),
+            Action::Skip => self.screen.skip(ctx),
+            Action::ScrollUp => self.screen.scroll_pages(1),
+            Action::ScrollDown => self.screen.scroll_pages(-1),
+            Action::Mute => self.toggle_mute(ctx),
+            // The window is switched by the main state
+            Action::Fullscreen => (),
+            Action::Up if keyboard => self.keyboard.move_cursor(0, -1),
+            Action::Down if keyboard => self.keyboard.move_cursor(0, 1),
+            Action::Left if keyboard => self.keyboard.move_cursor(-1, 0),
+            Action::Right if keyboard => self.keyboard.move_cursor(1, 0),
+            Action::Up | Action::Left => self.screen.select_choice(-1),
+            Action::Down | Action::Right => self.screen.select_choice(1),
+            Action::Confirm if keyboard => match self.keyboard.selected() {
+                VirtualKey::Char(ch) => self.term_input.type_text(&ch.to_string()),
+                VirtualKey::Space => self.term_input.type_text(" "),
+                VirtualKey::Back => self.term_input.backspace(),
+                VirtualKey::Enter => return self.act(ctx, Action::Submit),
+            },
+            Action::Confirm => {
+                if let Some(command) = self.screen.chosen() {
+                    return self.submit(ctx, &command);
+                }
+            }
+            Action::Keyboard if keyboard => self.keyboard.close(),
+            Action::Keyboard => self.keyboard.open(),
+            Action::Menu => self.options.open(),
+            Action::Choice(index) => {
+                let choice = match self.term_input.is_empty() {
+                    true => self.screen.choose(index),
+                    false => None,
+                };
+                self.chose_by_key = choice.is_some();
+                if let Some(command) = choice {
+                    return self.submit(ctx, &command);
+                }
+            }
+        }
+        Ok(())
+    }
 
+    /// Apply the input of the frame to the open options screen.
+    ///
+    /// The directions move between the bindings, `Submit` or `Confirm`
+    /// picks one and `Skip` or `Menu` closes the screen. While a key is
+    /// awaited, any other action cancels.
+    fn handle_options(&mut self, ctx: &mut Context, event: &InputEvent) {
+        match *event {
+            InputEvent::Action(Action::Mute) => self.toggle_mute(ctx),
+            InputEvent::Action(_) if self.options.is_rebinding() => self.options.cancel(),
+            InputEvent::Action(Action::Up) | InputEvent::Key(KeyCode::Up, _) => {
+                self.options.move_selection(-1)
+            }
+            InputEvent::Action(Action::Down) | InputEvent::Key(KeyCode::Down, _) => {
+                self.options.move_selection(1)
+            }
+            InputEvent::Action(Action::Submit | Action::Confirm) => self.options.confirm(),
+            InputEvent::Action(Action::Skip | Action::Menu) => self.options.close(),
+            _ => (),
+        }
+    }
+
+    /// Whether the next key pressed is bound on the options screen
+    pub fn is_rebinding(&self) -> bool {
+        self.options.is_rebinding()
+    }
+
+    /// Bind `key` to the action picked on the options screen.
+    pub fn rebind(&mut self, key: KeyCode) {
+        self.options.press(key);
+    }
+
+    /// Current key bindings
+    pub fn keymap(&self) -> &Keymap {
+        self.options.keymap()
+    }
+
+    /// Bindings changed on the options screen since the last call
+    pub fn take_keymap(&mut self) -> Option<Keymap> {
+        self.options.take_keymap()
+    }
+
+    /// Run `command` and follow the progress of the conversation.
+    fn submit(&mut self, ctx: &mut Context, command: &str) -> GameResult {
+        let progress = self.screen.submit(command);
+        // The prompt shows the username once the player has logged in
+        if let Some(profile) = self.screen.profile() {
+            self.term_input.set_profile(&profile);
+        }
+        match progress {
+            Progress::Idle => (),
+            Progress::Advanced => {
+                if let Some(sound) = self.screen.sound() {
+                    self.audio.play_stinger(ctx, &sound);
+                }
+                self.save()?
+            }
+            Progress::Printed | Progress::Cleared => self.save()?,
+            Progress::Ended => {
+                self.saves.clear(self.save_slot)?;
+                event::quit(ctx);
+            }
+        }
         Ok(())
     }
 
+    /// Silence the sounds, or bring them back.
+    pub fn toggle_mute(&mut self, ctx: &Context) {
+        let muted = !self.audio.is_muted();
+        self.audio.set_muted(ctx, muted);
+    }
+
     pub fn display(&self, ctx: &mut Context) -> GameResult {
+        match self.crt.as_ref() {
+            Some(crt) => crt.render(ctx, self.theme.background, |ctx| self.draw(ctx)),
+            None => self.draw(ctx),
+        }
+    }
+
+    /// Draw every part of the interface.
+    fn draw(&self, ctx: &mut Context) -> GameResult {
         // Display edges
         for edge in self.edges.iter() {
             edge.display(ctx)?;
@@ -63,55 +472,15 @@ impl GUI {
 
         // Display screen
         self.screen.display(ctx)?;
-
-        Ok(())
+        self.keyboard.display(ctx)?;
+        self.options.display(ctx)
     }
 
-    fn build_edges(ctx: &mut Context) -> GameResult<Vec<Edge>> {
+    fn build_edges(frame: &Frame, theme: &Theme, ctx: &mut Context) -> GameResult<Vec<Edge>> {
         let mut edges = Vec::new();
-
-        let edges_matrix = Self::compute_edges_matrix(ctx);
-        for row in edges_matrix.axis_iter(Axis(0)) {
-            let heights = row.slice(s![0, ..]);
-            let widths = row.slice(s![1, ..]);
-            let edge = Self::build_edge(heights.to_vec(), widths.to_vec(), ctx)?;
-            edges.push(edge);
+        for border in frame.borders() {
+            edges.extend(Edge::around(*border, theme, ctx)?);
         }
-
         Ok(edges)
     }
-
-    fn build_edge(heights: Vec<f32>, widths: Vec<f32>, ctx: &mut Context) -> GameResult<Edge> {
-        let start = Point2D::new(widths[0], heights[0]);
-        let end = Point2D::new(widths[1], heights[1]);
-
-        Ok(Edge::new(start, end, ctx)?)
-    }
-
-    fn compute_edges_matrix(ctx: &mut Context) -> Array3<f32> {
-        let (max_width, max_height) = graphics::drawable_size(ctx);
-        arr3(&[
-            // Upper [heights, widths]
-            [
-                [DEFAULT_MARGIN * 2.0, DEFAULT_MARGIN * 2.0],
-                [DEFAULT_MARGIN, max_width - DEFAULT_MARGIN],
-            ],
-            // Lower [heights, widths]
-            [
-                [max_height - LOWER_MARGIN, max_height - LOWER_MARGIN],
-                [DEFAULT_MARGIN, max_width - DEFAULT_MARGIN],
-            ],
-            // Left [heights, widths]
-            [
-                [DEFAULT_MARGIN * 2.0, max_height - LOWER_MARGIN],
-                [DEFAULT_MARGIN, DEFAULT_MARGIN],
-            ],
-            // Right [heights, widths]
-            [
-                [DEFAULT_MARGIN * 2.0, max_height - LOWER_MARGIN],
-                [max_width - DEFAULT_MARGIN, max_width - DEFAULT_MARGIN],
-            ],
-        ])
-    }
-
 }
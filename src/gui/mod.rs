@@ -1,5 +1,11 @@
 mod main;
 mod edge;
+mod crt;
+// Split panes are there for the panels added next to the screen
+#[allow(dead_code)]
+mod frame;
+mod keyboard;
+mod options;
 mod stats;
 mod command;
 mod screen;
@@ -0,0 +1,368 @@
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::input::{is_bindable, key_name, Action, Keymap, SavedKeys};
+use crate::theme::Theme;
+use crate::utils::Point2D;
+
+static TITLE: &str = "KEY BINDINGS";
+/// Width of the action names, in characters
+static NAME_WIDTH: usize = 13;
+static SELECTED_MARKER: &str = "> ";
+/// Space between the border of the screen and its text
+static PADDING: f32 = 10.0;
+
+/// Row of the options screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Entry {
+    Action(Action),
+    Reset,
+    Close,
+}
+
+impl Entry {
+    fn label(&self, keymap: &Keymap, rebinding: bool) -> String {
+        match self {
+            Entry::Action(action) => {
+                let keys = match rebinding {
+                    true => "press a key".to_string(),
+                    false => names(keymap, *action),
+                };
+                format!("{:<width$}{}", action.name(), keys, width = NAME_WIDTH)
+            }
+            Entry::Reset => "reset to defaults".to_string(),
+            Entry::Close => "close".to_string(),
+        }
+    }
+}
+
+/// Keys of `action` joined for display, `-` when it has none
+fn names(keymap: &Keymap, action: Action) -> String {
+    let keys: Vec<String> = keymap.keys_of(action).into_iter().map(key_name).collect();
+    match keys.is_empty() {
+        true => "-".to_string(),
+        false => keys.join(", "),
+    }
+}
+
+/// Bindings edited on the options screen and the highlighted row
+struct Bindings {
+    /// Bindings of the settings, restored by the reset row
+    defaults: Keymap,
+    keymap: Keymap,
+    entries: Vec<Entry>,
+    selected: usize,
+
+    /// Whether the next key pressed is bound to the selected action
+    rebinding: bool,
+
+    /// Outcome of the last change, shown under the rows
+    message: String,
+}
+
+impl Bindings {
+    fn new(defaults: Keymap, keymap: Keymap) -> Self {
+        let mut entries: Vec<Entry> = Action::all().map(Entry::Action).collect();
+        entries.extend([Entry::Reset, Entry::Close]);
+
+        Self {
+            defaults,
+            keymap,
+            entries,
+            selected: 0,
+            rebinding: false,
+            message: String::new(),
+        }
+    }
+
+    fn selected(&self) -> Entry {
+        self.entries[self.selected]
+    }
+
+    /// Move the highlight by `step` rows, wrapping around.
+    fn move_selection(&mut self, step: isize) {
+        if self.rebinding {
+            return;
+        }
+        let length = self.entries.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(length) as usize;
+    }
+
+    /// Apply the highlighted row, returns whether the bindings changed.
+    ///
+    /// An action waits for its new key, `Close` is left to the caller.
+    fn confirm(&mut self) -> bool {
+        match self.selected() {
+            Entry::Action(action) => {
+                self.rebinding = true;
+                self.message = format!("Press the new key for {}", action.name());
+                false
+            }
+            Entry::Reset => {
+                self.keymap = self.defaults.clone();
+                self.message = "Bindings reset to defaults".to_string();
+                true
+            }
+            Entry::Close => false,
+        }
+    }
+
+    /// Bind `key` to the action waiting for one, returns whether the
+    /// bindings changed.
+    ///
+    /// Keys typing text and keys already bound to another action are
+    /// refused.
+    fn press(&mut self, key: KeyCode) -> bool {
+        let action = match (self.rebinding, self.selected()) {
+            (true, Entry::Action(action)) => action,
+            _ => return false,
+        };
+        self.rebinding = false;
+
+        if !is_bindable(action, key) {
+            self.message = format!("{} can't be bound to {}", key_name(key), action.name());
+            return false;
+        }
+        match self.keymap.bind_key(action, key) {
+            Ok(()) => {
+                self.message = format!("{} bound to {}", key_name(key), action.name());
+                true
+            }
+            Err(other) => {
+                self.message = format!("{} is already bound to {}", key_name(key), other.name());
+                false
+            }
+        }
+    }
+
+    /// Stop waiting for a key.
+    fn cancel(&mut self) {
+        if self.rebinding {
+            self.rebinding = false;
+            self.message.clear();
+        }
+    }
+}
+
+/// Screen listing the key bindings, where the player rebinds them
+///
+/// Opened from the terminal and drawn over the whole screen area. Changes
+/// are saved at once and handed to the input queue by the GUI.
+pub struct OptionsScreen {
+    bindings: Bindings,
+    saved: SavedKeys,
+    is_open: bool,
+
+    /// Bindings changed since the last call to `take_keymap`
+    changed: bool,
+
+    area: Rect,
+    font: Font,
+    font_size: f32,
+    text_color: Color,
+    dim_color: Color,
+    highlight_color: Color,
+    background: Color,
+    border_color: Color,
+}
+
+impl OptionsScreen {
+    /// Screen editing `keymap`, which `defaults` are the bindings of the
+    /// settings.
+    pub fn new(
+        font: Font,
+        theme: &Theme,
+        defaults: Keymap,
+        keymap: Keymap,
+        saved: SavedKeys,
+    ) -> Self {
+        Self {
+            bindings: Bindings::new(defaults, keymap),
+            saved,
+            is_open: false,
+            changed: false,
+            area: Rect::new(0.0, 0.0, 0.0, 0.0),
+            font,
+            font_size: theme.font_size,
+            text_color: theme.text,
+            dim_color: theme.dim_text,
+            highlight_color: theme.input,
+            background: theme.background,
+            border_color: theme.border,
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.bindings.keymap
+    }
+
+    /// New bindings, when they changed since the last call
+    pub fn take_keymap(&mut self) -> Option<Keymap> {
+        match std::mem::take(&mut self.changed) {
+            true => Some(self.bindings.keymap.clone()),
+            false => None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn open(&mut self) {
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.bindings.cancel();
+        self.bindings.message.clear();
+        self.is_open = false;
+    }
+
+    /// Whether the next key pressed is taken by the screen
+    pub fn is_rebinding(&self) -> bool {
+        self.is_open && self.bindings.rebinding
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    pub fn move_selection(&mut self, step: isize) {
+        self.bindings.move_selection(step);
+    }
+
+    /// Apply the highlighted row.
+    pub fn confirm(&mut self) {
+        if self.bindings.selected() == Entry::Close {
+            self.close();
+        } else if self.bindings.confirm() {
+            self.persist();
+        }
+    }
+
+    /// Bind `key` to the action waiting for one.
+    pub fn press(&mut self, key: KeyCode) {
+        if self.bindings.press(key) {
+            self.persist();
+        }
+    }
+
+    /// Stop waiting for a key.
+    pub fn cancel(&mut self) {
+        self.bindings.cancel();
+    }
+
+    /// Save the bindings, or forget the saved ones when they are back to
+    /// the defaults.
+    fn persist(&mut self) {
+        self.changed = true;
+        let result = match self.bindings.keymap == self.bindings.defaults {
+            true => self.saved.clear(),
+            false => self.saved.save(&self.bindings.keymap.key_names()),
+        };
+        if let Err(err) = result {
+            self.bindings.message = format!("Could not save the bindings: {}", err);
+        }
+    }
+
+    pub fn display(&self, ctx: &mut Context) -> GameResult {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), self.area, self.background)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        let border = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), self.area, self.border_color)?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        let origin = Point2D::new(self.area.x + PADDING, self.area.y + PADDING);
+        let rows = ((self.area.h - PADDING * 2.0) / self.font_size).floor().max(4.0) as usize;
+        self.draw_line(ctx, TITLE, origin, self.text_color)?;
+
+        // The title, the message and the blank lines around the entries
+        // take four rows, scroll the entries to keep the highlight shown
+        let shown = rows - 4;
+        let first = (self.bindings.selected + 1).saturating_sub(shown);
+        let entries = self.bindings.entries.iter().enumerate().skip(first).take(shown);
+        for (row, (index, entry)) in entries.enumerate() {
+            let selected = index == self.bindings.selected;
+            let (marker, color) = match selected {
+                true => (SELECTED_MARKER, self.highlight_color),
+                false => ("  ", self.text_color),
+            };
+            let label = entry.label(&self.bindings.keymap, selected && self.bindings.rebinding);
+            let pos = origin + Point2D::new(0.0, (row + 2) as f32 * self.font_size);
+            self.draw_line(ctx, &format!("{}{}", marker, label), pos, color)?;
+        }
+
+        let pos = origin + Point2D::new(0.0, (rows - 1) as f32 * self.font_size);
+        self.draw_line(ctx, &self.bindings.message, pos, self.dim_color)
+    }
+
+    fn draw_line(&self, ctx: &mut Context, line: &str, pos: Point2D, color: Color) -> GameResult {
+        let text = Text::new((line, self.font, self.font_size));
+        graphics::draw(ctx, &text, (pos.as_vec(), color))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings() -> Bindings {
+        Bindings::new(Keymap::default(), Keymap::default())
+    }
+
+    fn select(bindings: &mut Bindings, entry: Entry) {
+        while bindings.selected() != entry {
+            bindings.move_selection(1);
+        }
+    }
+
+    #[test]
+    fn rebinds_the_selected_action() {
+        let mut bindings = bindings();
+        select(&mut bindings, Entry::Action(Action::Skip));
+
+        assert!(!bindings.press(KeyCode::F2));
+        assert!(!bindings.confirm());
+        bindings.move_selection(1);
+        assert_eq!(bindings.selected(), Entry::Action(Action::Skip));
+        assert!(bindings.press(KeyCode::F2));
+
+        assert_eq!(bindings.keymap.keys_of(Action::Skip), vec![KeyCode::F2]);
+        assert_eq!(bindings.keymap.key_action(KeyCode::Escape), None);
+        assert!(!bindings.rebinding);
+    }
+
+    #[test]
+    fn refuses_keys_typing_text() {
+        let mut bindings = bindings();
+        select(&mut bindings, Entry::Action(Action::Skip));
+        bindings.confirm();
+        assert!(!bindings.press(KeyCode::Space));
+        assert_eq!(bindings.message, "Space can't be bound to skip");
+
+        select(&mut bindings, Entry::Action(Action::Choice(0)));
+        bindings.confirm();
+        assert!(bindings.press(KeyCode::Key0));
+        assert_eq!(bindings.keymap.key_action(KeyCode::Key0), Some(Action::Choice(0)));
+    }
+
+    #[test]
+    fn refuses_conflicts_and_resets_to_defaults() {
+        let mut bindings = bindings();
+        select(&mut bindings, Entry::Action(Action::Skip));
+        bindings.confirm();
+        assert!(!bindings.press(KeyCode::F10));
+        assert_eq!(bindings.message, "F10 is already bound to mute");
+        assert_eq!(bindings.keymap, Keymap::default());
+
+        bindings.confirm();
+        assert!(bindings.press(KeyCode::F2));
+        select(&mut bindings, Entry::Reset);
+        assert!(bindings.confirm());
+        assert_eq!(bindings.keymap, Keymap::default());
+    }
+}
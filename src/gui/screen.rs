@@ -1,100 +1,469 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::time::Duration;
 
-use ggez::event::{quit, KeyCode};
-use ggez::graphics::Font;
+use ggez::graphics::{self, Color, Font, PxScale, Rect, Text, TextFragment};
 use ggez::{Context, GameResult};
 
-use super::edge::{Edge, Vector};
+use crate::dialogue::{Conversation, Profile, Progress, StoryFile, Variables};
+use crate::settings::GameSettings;
+use crate::theme::Theme;
+use crate::utils::layout;
+use crate::utils::markup::{Markup, Style};
+use crate::utils::{Point2D, Scrollback, StyledLine, TextMeasure};
 
-use crate::dialogue::Dialogue;
-use crate::utils::Point2D;
+static WHEEL_SCROLL_LINES: f32 = 3.0;
+/// Horizontal offset of the second pass used to draw bold text
+static BOLD_OFFSET: f32 = 1.0;
+/// Space between two completion matches listed on the same line
+static MATCHES_SEPARATOR: &str = "   ";
+/// Marker in front of the highlighted decision
+static CHOICE_MARKER: &str = "> ";
+/// Decisions numbered in the menu, one per `choice_` action
+static MAX_SHORTCUTS: usize = 9;
 
-static INNER_SCREEN_MARGIN: Point2D = Point2D { x: 10.0, y: 10.0 };
-static SCREEN_FONT_SIZE: f32 = 18.0;
+/// Measures text drawn with the screen font
+pub(super) struct FontMeasure<'a> {
+    pub ctx: &'a Context,
+    pub font: Font,
+    pub size: f32,
+}
 
-type DialogueHistory = Vec<Rc<RefCell<Dialogue>>>;
+impl TextMeasure for FontMeasure<'_> {
+    fn width(&self, text: &str) -> f32 {
+        let fragment = TextFragment::new(text)
+            .font(self.font)
+            .scale(PxScale::from(self.size));
+        Text::new(fragment).width(self.ctx)
+    }
+}
 
 pub struct Screen {
-    _edges: Box<Vec<Edge>>,
-    dialogue_history: DialogueHistory,
-    current_dialogue: Rc<RefCell<Dialogue>>,
+    font: Font,
+    font_size: f32,
+
+    /// Colours of the dialogue text and of echoed commands and matches
+    text_color: Color,
+    dim_color: Color,
+
+    /// Top left corner of the text area
+    anchor: Point2D,
+
+    /// Width of the text area
+    width: f32,
+
+    /// Number of lines that fit between the screen edges
+    rows: usize,
+
+    /// Completion matches listed under the current dialogue
+    matches: Vec<StyledLine>,
+
+    /// Decision highlighted with the gamepad or the mouse
+    choice: Option<usize>,
+
+    /// Lines of the decision menu at the end of the live lines
+    menu_lines: usize,
+
+    /// Lines moved to the history since the current dialogue was reached,
+    /// its own text included
+    printed_lines: usize,
+
+    /// Characters revealed by the last update
+    revealed: usize,
+
+    scrollback: Scrollback,
+    conversation: Conversation,
 }
 
 impl Screen {
-    pub fn new(edges: Box<Vec<Edge>>, font: Font) -> Self {
-        let screen_anchor = match edges.first() {
-            Some(edge) => *edge.get_start() + INNER_SCREEN_MARGIN,
-            None => panic!("Screen edges are not set."),
-        };
-        let action2 = Rc::new(RefCell::new(Dialogue::new(
-            "Nice you chose the only one we have".to_string(),
-            None,
-            Some(KeyCode::M),
-            1200.0,
-            Some(font),
-            SCREEN_FONT_SIZE,
-            Some(screen_anchor),
-        )));
-
-        let action1 = Rc::new(RefCell::new(Dialogue::new(
-            "What do you choose ? Milk or Tea ?".to_string(),
-            Some(vec![action2]),
-            None,
-            1200.0,
-            Some(font),
-            SCREEN_FONT_SIZE,
-            Some(screen_anchor),
-        )));
+    /// Screen showing `story` in the text `area`.
+    pub fn new(
+        area: Rect,
+        font: Font,
+        theme: &Theme,
+        story: &StoryFile,
+        settings: &GameSettings,
+    ) -> Self {
+        let (anchor, width, rows) = Self::text_area(area, theme.font_size);
+        let story = story.build(width, settings.text_speed);
 
         Self {
-            _edges: edges,
-            current_dialogue: action1,
-            dialogue_history: vec![],
+            font,
+            font_size: theme.font_size,
+            text_color: theme.text,
+            dim_color: theme.dim_text,
+            anchor,
+            width,
+            rows,
+            matches: Vec::new(),
+            choice: None,
+            menu_lines: 0,
+            printed_lines: 0,
+            revealed: 0,
+            scrollback: Scrollback::new(settings.scrollback_lines),
+            conversation: Conversation::new(story),
         }
     }
 
-    pub fn update(&mut self, ctx: &mut Context, user_input: Option<KeyCode>) -> GameResult {
+    /// Top left corner, width and number of rows of the text `area`
+    fn text_area(area: Rect, font_size: f32) -> (Point2D, f32, usize) {
+        let anchor = Point2D::new(area.x, area.y);
+        let rows = (area.h / font_size).floor().max(1.0) as usize;
+        (anchor, area.w.max(font_size), rows)
+    }
+
+    /// Fit the text area in a new `area`, wrapping every line again when
+    /// the width changed.
+    pub fn resize(&mut self, ctx: &Context, area: Rect) {
+        let (anchor, width, rows) = Self::text_area(area, self.font_size);
+        self.anchor = anchor;
+        self.rows = rows;
+        if width == self.width {
+            return;
+        }
+        self.width = width;
+
+        let measure = self.measure(ctx);
+        self.conversation.resize(width, &measure);
+
+        // The lines of the current dialogue are wrapped on their own to
+        // keep counting them
+        let history = self.scrollback.history();
+        let (earlier, printed) = history.split_at(history.len().saturating_sub(self.printed_lines));
+        let printed = layout::rewrap(printed, width, &measure);
+        let mut lines = layout::rewrap(earlier, width, &measure);
+        self.printed_lines = printed.len();
+        lines.extend(printed);
+        self.matches = layout::rewrap(&self.matches, width, &measure);
+
+        self.scrollback.set_history(lines);
+        self.refresh_live();
+    }
+
+    fn measure<'a>(&self, ctx: &'a Context) -> FontMeasure<'a> {
+        FontMeasure {
+            ctx,
+            font: self.font,
+            size: self.font_size,
+        }
+    }
+
+    /// Version of the story shown on the screen
+    pub fn story_version(&self) -> u32 {
+        self.conversation.story_version()
+    }
+
+    /// Id of the current dialogue, the lines printed before it as markup
+    /// and the story variables
+    pub fn snapshot(&self) -> (String, Vec<String>, Variables) {
+        let node = self.conversation.current_id();
+        let history = self.scrollback.history();
+        // The current dialogue is shown again when the save is restored
+        let kept = history.len().saturating_sub(self.printed_lines);
+        let history = history[..kept].iter().map(StyledLine::to_markup).collect();
+        (node, history, self.conversation.variables().clone())
+    }
+
+    /// Working directory of the shell, saved with the snapshot
+    pub fn cwd(&self) -> &str {
+        self.conversation.cwd()
+    }
+
+    /// Continue the conversation from the dialogue with id `node`, see
+    /// `Conversation::restore`.
+    pub fn restore(
+        &mut self,
+        node: &str,
+        history: Vec<String>,
+        variables: Variables,
+        cwd: Option<&str>,
+    ) -> bool {
+        self.scrollback
+            .extend(history.iter().map(|line| StyledLine::from_markup(line)));
+        self.conversation.restore(node, variables, cwd)
+    }
+
+    /// Reveal the text due in the `elapsed` time.
+    pub fn update(&mut self, ctx: &Context, elapsed: Duration) {
+        self.revealed = 0;
+
         // Check if the whole text was parsed and displayed
-        let new_dialogue_ref = Rc::clone(&self.current_dialogue);
-        let mut current_dialogue = new_dialogue_ref.borrow_mut();
-        if !current_dialogue.has_text_updated {
-            current_dialogue.update();
-            return Ok(());
+        if !self.conversation.is_revealed() {
+            let measure = self.measure(ctx);
+            self.revealed = self.conversation.update(elapsed, &measure);
+            self.refresh_live();
+        }
+    }
+
+    /// Advance the dialogue with the typed `command`, or run it in the
+    /// shell. Ignored until the text is revealed.
+    pub fn submit(&mut self, command: &str) -> Progress {
+        if !self.conversation.is_revealed() {
+            return Progress::Idle;
         }
 
-        let user_input_event = match user_input {
-            Some(i) => i,
-            None => KeyCode::Escape,
-        };
+        // The decision menu is not kept in the history
+        self.choice = None;
+        self.set_live(false);
 
         // Check user input to advance the dialogue
-        let next_dialogue_result = current_dialogue.advance(user_input_event);
-        if next_dialogue_result.0 {
-            match next_dialogue_result.1 {
-                Some(new_dialogue) => {
-                    let mut last_display_cursor = current_dialogue.get_last_display_pos();
-                    last_display_cursor += Point2D::new(0.0, 18.0);
-
-                    self.dialogue_history
-                        .push(Rc::clone(&self.current_dialogue));
-                    self.current_dialogue = Rc::clone(&new_dialogue);
-                    self.current_dialogue
-                        .borrow_mut()
-                        .set_position(last_display_cursor);
-                }
-                None => quit(ctx)
+        let progress = self.conversation.submit(command);
+        match progress {
+            Progress::Advanced => {
+                self.scrollback.commit();
+                self.printed_lines = 0;
+            }
+            Progress::Printed => {
+                self.printed_lines += self.scrollback.commit();
+                self.scrollback.extend(vec![self.echo(command)]);
+                self.printed_lines += 1;
+            }
+            Progress::Cleared => {
+                self.scrollback.clear();
+                self.printed_lines = 0;
+            }
+            Progress::Idle | Progress::Ended => {
+                self.refresh_live();
+                return progress;
             }
         }
+        self.scrollback.scroll_to_bottom();
+        self.matches.clear();
+        self.refresh_live();
 
-        Ok(())
+        progress
+    }
+
+    /// Show the rest of the current dialogue at once.
+    pub fn skip(&mut self, ctx: &Context) {
+        if !self.conversation.is_revealed() {
+            self.conversation.skip(&self.measure(ctx));
+            self.refresh_live();
+        }
+    }
+
+    /// Move the highlight `step` decisions down, wrapping around.
+    ///
+    /// The first step highlights the first or last decision.
+    pub fn select_choice(&mut self, step: isize) {
+        let count = self.conversation.decisions().len() as isize;
+        self.choice = match (self.choice, count) {
+            (_, 0) => None,
+            (None, _) if step < 0 => Some(count as usize - 1),
+            (None, _) => Some(0),
+            (Some(index), _) => Some((index as isize + step).rem_euclid(count) as usize),
+        };
+        self.refresh_live();
+    }
+
+    /// Command of the highlighted decision
+    pub fn chosen(&self) -> Option<String> {
+        self.choice.and_then(|index| self.choose(index))
+    }
+
+    /// Command of the decision numbered `index + 1` in the menu
+    pub fn choose(&self, index: usize) -> Option<String> {
+        if self.menu_lines == 0 {
+            return None;
+        }
+        self.conversation.decisions().get(index).cloned()
+    }
+
+    /// Highlight the decision under the mouse at `pos`, if any.
+    pub fn hover(&mut self, pos: Point2D) {
+        let choice = self.choice_at(pos);
+        if choice != self.choice {
+            self.choice = choice;
+            self.refresh_live();
+        }
+    }
+
+    /// Index of the decision drawn at `pos`
+    pub fn choice_at(&self, pos: Point2D) -> Option<usize> {
+        let inside = pos.x >= self.anchor.x
+            && pos.x <= self.anchor.x + self.width
+            && pos.y >= self.anchor.y;
+        if !inside || self.menu_lines == 0 {
+            return None;
+        }
+
+        let row = ((pos.y - self.anchor.y) / self.font_size) as usize;
+        if row >= self.scrollback.visible(self.rows).count() {
+            return None;
+        }
+        let line = self.scrollback.first_visible(self.rows) + row;
+        // The menu starts with a blank line
+        let first_choice = self.scrollback.line_count() - self.menu_lines + 1;
+        line.checked_sub(first_choice)
+    }
+
+    /// Numbered decisions listed under the dialogue once it is revealed,
+    /// the highlighted one marked
+    fn menu(&self) -> Vec<StyledLine> {
+        let decisions = self.conversation.decisions();
+        if decisions.is_empty() || !self.conversation.is_revealed() {
+            return Vec::new();
+        }
+
+        let mut lines = vec![StyledLine::new()];
+        for (index, command) in decisions.iter().enumerate() {
+            let (marker, style) = match Some(index) == self.choice {
+                true => (CHOICE_MARKER, Style { color: Some(self.text_color), bold: true }),
+                false => ("  ", Style { color: Some(self.dim_color), bold: false }),
+            };
+            let number = match index < MAX_SHORTCUTS {
+                true => format!("{}. ", index + 1),
+                false => "   ".to_string(),
+            };
+            let mut line = StyledLine::new();
+            for ch in marker.chars().chain(number.chars()).chain(command.chars()) {
+                line.push(ch, style);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Line repeating a built-in command above its output
+    fn echo(&self, command: &str) -> StyledLine {
+        let mut line = StyledLine::new();
+        let style = Style {
+            color: Some(self.dim_color),
+            bold: false,
+        };
+        for ch in format!("$ {}", command).chars() {
+            line.push(ch, style);
+        }
+        line
+    }
+
+    pub fn variables(&self) -> &Variables {
+        self.conversation.variables()
+    }
+
+    /// Number of characters revealed by the last update
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    /// Stinger of the current dialogue, see `Conversation::sound`.
+    pub fn sound(&self) -> Option<String> {
+        self.conversation.sound()
+    }
+
+    /// Ask the player for a username, see `Conversation::start_onboarding`.
+    pub fn start_onboarding(&mut self, seed: u64) {
+        self.conversation.start_onboarding(seed);
+        self.refresh_live();
+    }
+
+    pub fn is_onboarding(&self) -> bool {
+        self.conversation.is_onboarding()
+    }
+
+    pub fn profile(&self) -> Option<Profile> {
+        self.conversation.profile()
+    }
+
+    pub fn set_profile(&mut self, profile: &Profile) {
+        self.conversation.set_profile(profile);
+    }
+
+    /// Whether the story has no decisions left to take
+    pub fn is_ending(&self) -> bool {
+        self.conversation.is_ending()
+    }
+
+    /// Commands the player can complete at the prompt
+    pub fn completions(&self) -> Vec<String> {
+        self.conversation.commands()
+    }
+
+    /// List completion matches under the current dialogue, replacing the
+    /// ones listed before.
+    pub fn list_matches(&mut self, ctx: &Context, matches: &[String]) {
+        let style = Style {
+            color: Some(self.dim_color),
+            bold: false,
+        };
+        let items: Vec<Markup> = matches
+            .join(MATCHES_SEPARATOR)
+            .chars()
+            .map(|ch| Markup::Char(ch, style))
+            .collect();
+        let measure = self.measure(ctx);
+
+        self.matches = layout::to_lines(&layout::wrap(&items, self.width, &measure));
+
+        self.refresh_live();
+        self.scrollback.scroll_to_bottom();
+    }
+
+    /// Show the current dialogue followed by the listed matches and the
+    /// decision menu.
+    fn refresh_live(&mut self) {
+        self.set_live(true);
+    }
+
+    fn set_live(&mut self, with_menu: bool) {
+        let mut lines = self.conversation.lines();
+        lines.extend(self.matches.iter().cloned());
+        let menu = match with_menu {
+            true => self.menu(),
+            false => Vec::new(),
+        };
+        self.menu_lines = menu.len();
+        lines.extend(menu);
+        self.scrollback.set_live(lines);
     }
 
     pub fn display(&self, ctx: &mut Context) -> GameResult {
-        for dialogue in self.dialogue_history.iter() {
-            dialogue.borrow().display(ctx)?
+        for (row, line) in self.scrollback.visible(self.rows).enumerate() {
+            let pos = self.anchor + Point2D::new(0.0, row as f32 * self.font_size);
+            self.draw_line(ctx, line, pos)?;
+        }
+        Ok(())
+    }
+
+    /// Draw a styled line, going over bold spans a second time slightly
+    /// to the right since the font has a single weight.
+    fn draw_line(&self, ctx: &mut Context, line: &StyledLine, pos: Point2D) -> GameResult {
+        let mut text = Text::default();
+        let mut bold_text = Text::default();
+        for span in line.spans.iter() {
+            let color = span.style.color.unwrap_or(self.text_color);
+            text.add(self.fragment(&span.text, color));
+
+            let bold_color = match span.style.bold {
+                true => color,
+                false => Color::new(0.0, 0.0, 0.0, 0.0),
+            };
+            bold_text.add(self.fragment(&span.text, bold_color));
+        }
+
+        graphics::draw(ctx, &text, (pos.as_vec(),))?;
+        if line.spans.iter().any(|span| span.style.bold) {
+            let bold_pos = pos + Point2D::new(BOLD_OFFSET, 0.0);
+            graphics::draw(ctx, &bold_text, (bold_pos.as_vec(),))?;
         }
-        self.current_dialogue.borrow().display(ctx)?;
         Ok(())
     }
+
+    fn fragment(&self, text: &str, color: Color) -> TextFragment {
+        TextFragment::new(text)
+            .font(self.font)
+            .scale(PxScale::from(self.font_size))
+            .color(color)
+    }
+
+    /// Move `pages` screens back through the history, forward when
+    /// negative.
+    pub fn scroll_pages(&mut self, pages: isize) {
+        let page = self.rows.saturating_sub(1).max(1) as isize;
+        self.scrollback.scroll(pages * page, self.rows);
+    }
+
+    /// Scroll the history by the lines the mouse wheel turned.
+    pub fn scroll_wheel(&mut self, wheel: f32) {
+        let lines = (wheel * WHEEL_SCROLL_LINES).round() as isize;
+        self.scrollback.scroll(lines, self.rows);
+    }
 }
@@ -1,94 +1,556 @@
-use ggez::graphics::{self, Font, Text};
+use std::time::Duration;
+
+use ggez::graphics::{self, Color, Font, PxScale, Rect, Text, TextFragment};
 use ggez::{Context, GameResult};
 
-use crate::utils::Point2D;
+use crate::dialogue::{Value, Variables, PLAYER_VARIABLE, TERMINAL_VARIABLE};
+use crate::theme::Theme;
+use crate::utils::{Point2D, TextMeasure};
+
+use super::screen::FontMeasure;
 
 static STATS_SEPARATOR: &str = "  |  ";
-static STATS_FONT_SIZE: f32 = 18.0;
-static STATS_FONT_PATH: &str = "/fonts/RobotoMono-VariableFont_wght.ttf";
+
+/// In-game time when the story starts, in minutes after midnight
+static CLOCK_START: u32 = 23 * 60 + 42;
+/// In-game minutes passing every real second
+static CLOCK_SCALE: f32 = 1.0;
+static TRACE_METER_WIDTH: usize = 10;
+
+/// Story variable holding the host the terminal is connected to
+static CONNECTION_VARIABLE: &str = "connection";
+/// Story variable holding the trace level, from 0 to 100
+static TRACE_VARIABLE: &str = "trace";
+/// Story variable holding the name of the current chapter
+static CHAPTER_VARIABLE: &str = "chapter";
+
+/// Side of the header bar a stat is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
 
 pub trait Stat {
     fn get_name(&self) -> String;
     fn get_value(&self) -> String;
-    fn build(&self) -> String {
-        format!("{}: {}", self.get_name(), self.get_value())
+
+    /// Colour the value is drawn with, the theme's text colour when `None`
+    fn get_color(&self) -> Option<Color> {
+        None
     }
+
+    fn get_align(&self) -> Align {
+        Align::Left
+    }
+
+    /// Follow the game after `elapsed` time, returns whether the displayed
+    /// value changed.
+    fn update(&mut self, _elapsed: Duration, _variables: &Variables) -> bool {
+        false
+    }
+
+    /// In-game time kept in the save, in minutes after midnight of the
+    /// first day
+    fn get_time(&self) -> Option<u32> {
+        None
+    }
+
+    /// Continue from the in-game time of a save.
+    fn set_time(&mut self, _minutes: u32) {}
 }
 
-/// Player chosen username
+/// Player chosen username, from the `player` variable
 pub struct PlayerName {
-    display_name: String,
-    display_value: String,
+    name: String,
 }
 impl PlayerName {
-    pub fn new(value: String) -> Box<Self> {
-        Box::new(Self {
-            display_name: "Username".to_string(),
-            display_value: value,
-        })
+    pub fn new() -> Box<Self> {
+        Box::new(Self { name: String::new() })
     }
 }
 impl Stat for PlayerName {
     fn get_name(&self) -> String {
-        self.display_name.to_owned()
+        "Username".to_string()
     }
 
     fn get_value(&self) -> String {
-        self.display_value.to_owned()
+        self.name.to_owned()
+    }
+
+    fn update(&mut self, _elapsed: Duration, variables: &Variables) -> bool {
+        update_text(&mut self.name, variables, PLAYER_VARIABLE)
     }
 }
 
-/// Terminal Name used as the game terminal change
+/// Id of the player's terminal, from the `terminal` variable
 pub struct TerminalName {
-    display_name: String,
-    display_value: String,
+    id: String,
 }
 impl TerminalName {
-    pub fn new(value: String) -> Box<Self> {
+    pub fn new() -> Box<Self> {
+        Box::new(Self { id: String::new() })
+    }
+}
+impl Stat for TerminalName {
+    fn get_name(&self) -> String {
+        "Terminal".to_string()
+    }
+
+    fn get_value(&self) -> String {
+        self.id.to_owned()
+    }
+
+    fn update(&mut self, _elapsed: Duration, variables: &Variables) -> bool {
+        update_text(&mut self.id, variables, TERMINAL_VARIABLE)
+    }
+}
+
+/// Copy the variable `name` into `value`, returns whether it changed.
+fn update_text(value: &mut String, variables: &Variables, name: &str) -> bool {
+    let text = variables.get(name).map(Value::to_string).unwrap_or_default();
+    let changed = text != *value;
+    *value = text;
+    changed
+}
+
+/// In-game time of day, running faster than real time
+pub struct Clock {
+    elapsed: Duration,
+    minutes: u32,
+}
+impl Clock {
+    pub fn new() -> Box<Self> {
         Box::new(Self {
-            display_name: "Terminal".to_string(),
-            display_value: value,
+            elapsed: Duration::ZERO,
+            minutes: CLOCK_START,
         })
     }
 }
-impl Stat for TerminalName {
+impl Stat for Clock {
+    fn get_name(&self) -> String {
+        "Time".to_string()
+    }
+
+    fn get_value(&self) -> String {
+        let minutes = self.minutes % (24 * 60);
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+
+    fn get_align(&self) -> Align {
+        Align::Right
+    }
+
+    fn update(&mut self, elapsed: Duration, _variables: &Variables) -> bool {
+        self.elapsed += elapsed;
+        let minutes = CLOCK_START + (self.elapsed.as_secs_f32() * CLOCK_SCALE) as u32;
+        let changed = minutes != self.minutes;
+        self.minutes = minutes;
+        changed
+    }
+
+    fn get_time(&self) -> Option<u32> {
+        Some(self.minutes)
+    }
+
+    fn set_time(&mut self, minutes: u32) {
+        let game_minutes = minutes.saturating_sub(CLOCK_START) as f32;
+        self.elapsed = Duration::from_secs_f32(game_minutes / CLOCK_SCALE);
+        self.minutes = minutes.max(CLOCK_START);
+    }
+}
+
+/// Host the terminal is connected to, from the `connection` variable
+pub struct Connection {
+    host: Option<String>,
+}
+impl Connection {
+    pub fn new() -> Box<Self> {
+        Box::new(Self { host: None })
+    }
+}
+impl Stat for Connection {
     fn get_name(&self) -> String {
-        self.display_name.to_owned()
+        "Link".to_string()
     }
 
     fn get_value(&self) -> String {
-        self.display_value.to_owned()
+        match &self.host {
+            Some(host) => host.to_owned(),
+            None => "offline".to_string(),
+        }
+    }
+
+    fn get_color(&self) -> Option<Color> {
+        match self.host {
+            Some(_) => Some(Color::GREEN),
+            None => Some(Color::RED),
+        }
+    }
+
+    fn get_align(&self) -> Align {
+        Align::Right
+    }
+
+    fn update(&mut self, _elapsed: Duration, variables: &Variables) -> bool {
+        let host = match variables.get(CONNECTION_VARIABLE) {
+            Some(value) if value.is_truthy() => Some(value.to_string()),
+            _ => None,
+        };
+        let changed = host != self.host;
+        self.host = host;
+        changed
     }
 }
 
+/// How close the player is to being traced, from the `trace` variable
+pub struct Trace {
+    level: i64,
+}
+impl Trace {
+    pub fn new() -> Box<Self> {
+        Box::new(Self { level: 0 })
+    }
+}
+impl Stat for Trace {
+    fn get_name(&self) -> String {
+        "Trace".to_string()
+    }
+
+    fn get_value(&self) -> String {
+        let filled = self.level as usize * TRACE_METER_WIDTH / 100;
+        format!(
+            "[{}{}] {}%",
+            "#".repeat(filled),
+            "-".repeat(TRACE_METER_WIDTH - filled),
+            self.level
+        )
+    }
+
+    fn get_color(&self) -> Option<Color> {
+        match self.level {
+            0..=39 => Some(Color::GREEN),
+            40..=79 => Some(Color::YELLOW),
+            _ => Some(Color::RED),
+        }
+    }
+
+    fn get_align(&self) -> Align {
+        Align::Right
+    }
+
+    fn update(&mut self, _elapsed: Duration, variables: &Variables) -> bool {
+        let level = match variables.get(TRACE_VARIABLE) {
+            Some(Value::Int(level)) => (*level).clamp(0, 100),
+            _ => 0,
+        };
+        let changed = level != self.level;
+        self.level = level;
+        changed
+    }
+}
+
+/// Name of the current chapter, from the `chapter` variable
+pub struct Chapter {
+    name: String,
+}
+impl Chapter {
+    pub fn new() -> Box<Self> {
+        Box::new(Self { name: String::new() })
+    }
+}
+impl Stat for Chapter {
+    fn get_name(&self) -> String {
+        "Chapter".to_string()
+    }
+
+    fn get_value(&self) -> String {
+        self.name.to_owned()
+    }
+
+    fn update(&mut self, _elapsed: Duration, variables: &Variables) -> bool {
+        update_text(&mut self.name, variables, CHAPTER_VARIABLE)
+    }
+}
+
+/// How the stats of the header are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Form {
+    /// `Name: value`
+    Labelled,
+    /// Values only, used when the names don't fit
+    Values,
+}
+
+/// Stats shown in the header, the first ones of each section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fit {
+    form: Form,
+    left: usize,
+    right: usize,
+}
+
 /// Factory to build and display all stats
 ///
-/// Stats should implement the `Stat` trait
+/// Stats should implement the `Stat` trait. The header text is rebuilt
+/// only when a stat reports a new value or the header is resized. When
+/// both sections don't fit the width, the names are left out, then the last
+/// stats of the widest section are dropped.
 pub struct GUIStats {
     font: Font,
+    font_size: f32,
+    text_color: Color,
     stats: Vec<Box<dyn Stat>>,
+
+    /// Area of the header bar in the window
+    area: Rect,
+
+    /// Rendered left and right sections of the header
+    left: Text,
+    right: Text,
 }
 
 impl GUIStats {
-    pub fn new(stats: Vec<Box<dyn Stat>>, ctx: &mut Context) -> GameResult<Self> {
-        let font = Font::new(ctx, STATS_FONT_PATH)?;
+    pub fn new(stats: Vec<Box<dyn Stat>>, font: Font, theme: &Theme) -> Self {
+        Self {
+            font,
+            font_size: theme.font_size,
+            text_color: theme.text,
+            stats,
+            area: Rect::new(0.0, 0.0, 0.0, 0.0),
+            left: Text::default(),
+            right: Text::default(),
+        }
+    }
+
+    /// Move the header bar, following the layout of the window.
+    pub fn set_area(&mut self, ctx: &Context, area: Rect) {
+        self.area = area;
+        self.render(ctx);
+    }
+
+    /// Update every stat, rendering the header again if one changed.
+    pub fn update(&mut self, ctx: &Context, elapsed: Duration, variables: &Variables) {
+        let mut changed = false;
+        for stat in self.stats.iter_mut() {
+            changed |= stat.update(elapsed, variables);
+        }
+
+        if changed {
+            self.render(ctx);
+        }
+    }
 
-        Ok(Self { font, stats })
+    /// In-game time of the clock, to keep in the save
+    pub fn time(&self) -> Option<u32> {
+        self.stats.iter().find_map(|stat| stat.get_time())
+    }
+
+    /// Continue the clock from the in-game time of a save.
+    pub fn set_time(&mut self, ctx: &Context, minutes: u32) {
+        for stat in self.stats.iter_mut() {
+            stat.set_time(minutes);
+        }
+        self.render(ctx);
     }
 
     pub fn display(&self, ctx: &mut Context) -> GameResult {
-        let dest_point = Point2D::new(20.0, 15.0);
-        let text = Text::new((self.build_stats_text_body(), self.font, STATS_FONT_SIZE));
+        let left_pos = Point2D::new(self.area.left(), self.area.top());
+        graphics::draw(ctx, &self.left, (left_pos.as_vec(),))?;
+
+        let right_x = self.area.right() - self.right.width(ctx);
+        let right_pos = Point2D::new(right_x, self.area.top());
+        graphics::draw(ctx, &self.right, (right_pos.as_vec(),))
+    }
+
+    fn render(&mut self, ctx: &Context) {
+        let measure = FontMeasure {
+            ctx,
+            font: self.font,
+            size: self.font_size,
+        };
+        let fit = self.fit(&measure);
+        self.left = self.build_section(Align::Left, fit.form, fit.left);
+        self.right = self.build_section(Align::Right, fit.form, fit.right);
+    }
+
+    /// Longest form of the header fitting the width of its area
+    fn fit(&self, measure: &dyn TextMeasure) -> Fit {
+        let width = |align, form, count| {
+            let text: String = self
+                .pieces(align, form, count)
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect();
+            measure.width(&text)
+        };
+        let gap = measure.width(STATS_SEPARATOR);
+        let fits = |fit: &Fit| {
+            width(Align::Left, fit.form, fit.left) + gap + width(Align::Right, fit.form, fit.right)
+                <= self.area.w
+        };
+
+        let mut fit = Fit {
+            form: Form::Labelled,
+            left: self.aligned(Align::Left).count(),
+            right: self.aligned(Align::Right).count(),
+        };
+        if fits(&fit) {
+            return fit;
+        }
+
+        fit.form = Form::Values;
+        while !fits(&fit) && fit.left + fit.right > 0 {
+            let left = width(Align::Left, fit.form, fit.left);
+            let right = width(Align::Right, fit.form, fit.right);
+            match fit.left > 0 && left >= right {
+                true => fit.left -= 1,
+                false => fit.right -= 1,
+            }
+        }
+        fit
+    }
+
+    fn aligned(&self, align: Align) -> impl Iterator<Item = &dyn Stat> {
+        self.stats
+            .iter()
+            .map(|stat| stat.as_ref())
+            .filter(move |stat| stat.get_align() == align)
+    }
+
+    /// Text of the first `count` stats of a section, with their colours
+    fn pieces(&self, align: Align, form: Form, count: usize) -> Vec<(String, Color)> {
+        let mut pieces = Vec::new();
+        for (index, stat) in self.aligned(align).take(count).enumerate() {
+            if index > 0 {
+                pieces.push((STATS_SEPARATOR.to_string(), self.text_color));
+            }
+            if form == Form::Labelled {
+                pieces.push((format!("{}: ", stat.get_name()), self.text_color));
+            }
+            let color = stat.get_color().unwrap_or(self.text_color);
+            pieces.push((stat.get_value(), color));
+        }
+        pieces
+    }
+
+    fn build_section(&self, align: Align, form: Form, count: usize) -> Text {
+        let mut text = Text::default();
+        for (piece, color) in self.pieces(align, form, count) {
+            text.add(self.fragment(piece, color));
+        }
+        text
+    }
+
+    fn fragment(&self, text: String, color: Color) -> TextFragment {
+        TextFragment::new(text)
+            .font(self.font)
+            .scale(PxScale::from(self.font_size))
+            .color(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::layout::MonoMeasure;
+
+    static MONO: MonoMeasure = MonoMeasure { advance: 10.0 };
+
+    fn variables(entries: &[(&str, Value)]) -> Variables {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn clock_changes_once_per_game_minute() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.get_value(), "23:42");
+
+        assert!(!clock.update(Duration::from_millis(500), &Variables::new()));
+        assert!(clock.update(Duration::from_millis(500), &Variables::new()));
+        assert_eq!(clock.get_value(), "23:43");
+
+        clock.update(Duration::from_secs(20), &Variables::new());
+        assert_eq!(clock.get_value(), "00:03");
+    }
+
+    #[test]
+    fn clock_resumes_from_a_saved_time() {
+        let mut clock = Clock::new();
+        clock.set_time(24 * 60 + 5);
+        assert_eq!(clock.get_time(), Some(24 * 60 + 5));
+        assert_eq!(clock.get_value(), "00:05");
+
+        clock.update(Duration::from_secs(1), &Variables::new());
+        assert_eq!(clock.get_value(), "00:06");
+        assert_eq!(Trace::new().get_time(), None);
+    }
+
+    #[test]
+    fn connection_follows_the_story_variable() {
+        let mut connection = Connection::new();
+        assert_eq!(connection.get_value(), "offline");
+        assert_eq!(connection.get_color(), Some(Color::RED));
+
+        let online = variables(&[("connection", Value::Text("10.0.0.4".to_string()))]);
+        assert!(connection.update(Duration::ZERO, &online));
+        assert!(!connection.update(Duration::ZERO, &online));
+        assert_eq!(connection.get_value(), "10.0.0.4");
+        assert_eq!(connection.get_color(), Some(Color::GREEN));
+    }
+
+    #[test]
+    fn trace_meter_fills_and_changes_colour() {
+        let mut trace = Trace::new();
+        assert_eq!(trace.get_value(), "[----------] 0%");
+
+        trace.update(Duration::ZERO, &variables(&[("trace", Value::Int(45))]));
+        assert_eq!(trace.get_value(), "[####------] 45%");
+        assert_eq!(trace.get_color(), Some(Color::YELLOW));
+
+        trace.update(Duration::ZERO, &variables(&[("trace", Value::Int(250))]));
+        assert_eq!(trace.get_value(), "[##########] 100%");
+        assert_eq!(trace.get_color(), Some(Color::RED));
+    }
+
+    #[test]
+    fn names_follow_the_player_profile() {
+        let mut name = PlayerName::new();
+        assert_eq!(name.get_value(), "");
 
-        graphics::draw(ctx, &text, (dest_point.as_vec(),))
+        let profile = variables(&[("player", Value::Text("vlad".to_string()))]);
+        assert!(name.update(Duration::from_secs(1), &profile));
+        assert!(!name.update(Duration::from_secs(1), &profile));
+        assert_eq!(name.get_value(), "vlad");
+        assert_eq!(name.get_align(), Align::Left);
     }
 
-    fn build_stats_text_body(&self) -> String {
-        let mut body: Vec<String> = Vec::new();
-        for stat in self.stats.iter() {
-            body.push(stat.build());
+    #[test]
+    fn header_shortens_to_fit_its_width() {
+        let mut stats = GUIStats::new(
+            vec![PlayerName::new(), Chapter::new(), Trace::new(), Clock::new()],
+            Font::default(),
+            &Theme::default(),
+        );
+        let profile = variables(&[
+            ("player", Value::Text("vlad".to_string())),
+            ("chapter", Value::Text("Intro".to_string())),
+        ]);
+        for stat in stats.stats.iter_mut() {
+            stat.update(Duration::ZERO, &profile);
         }
 
-        body.join(STATS_SEPARATOR)
+        let mut fit = |width| {
+            stats.area.w = width;
+            stats.fit(&MONO)
+        };
+        // Sections of 33 and 38 characters with names, 14 and 25 without
+        assert_eq!(fit(800.0), Fit { form: Form::Labelled, left: 2, right: 2 });
+        assert_eq!(fit(500.0), Fit { form: Form::Values, left: 2, right: 2 });
+        assert_eq!(fit(300.0), Fit { form: Form::Values, left: 2, right: 0 });
+        assert_eq!(fit(80.0), Fit { form: Form::Values, left: 0, right: 0 });
     }
 }
@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use ggez::event::{Button, KeyCode};
+use ggez::{GameError, GameResult};
+
+/// What the player asks the game to do, whatever key or button they use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Run the line typed at the prompt
+    Submit,
+
+    /// Complete the line typed at the prompt
+    Complete,
+
+    /// Show the rest of the text at once
+    Skip,
+
+    /// Move a page through the screen history
+    ScrollUp,
+    ScrollDown,
+
+    Fullscreen,
+    Mute,
+
+    /// Move the highlight through the decisions or the virtual keyboard
+    Up,
+    Down,
+    Left,
+    Right,
+
+    /// Pick the highlighted decision or virtual key
+    Confirm,
+
+    /// Open or close the virtual keyboard
+    Keyboard,
+
+    /// Open or close the options screen
+    Menu,
+
+    /// Pick a decision by its number, counted from zero, when the prompt
+    /// is empty
+    Choice(usize),
+}
+
+/// Actions with the name used in the settings
+static ACTIONS: [(&str, Action); 23] = [
+    ("submit", Action::Submit),
+    ("complete", Action::Complete),
+    ("skip", Action::Skip),
+    ("scroll_up", Action::ScrollUp),
+    ("scroll_down", Action::ScrollDown),
+    ("fullscreen", Action::Fullscreen),
+    ("mute", Action::Mute),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("confirm", Action::Confirm),
+    ("keyboard", Action::Keyboard),
+    ("menu", Action::Menu),
+    ("choice_1", Action::Choice(0)),
+    ("choice_2", Action::Choice(1)),
+    ("choice_3", Action::Choice(2)),
+    ("choice_4", Action::Choice(3)),
+    ("choice_5", Action::Choice(4)),
+    ("choice_6", Action::Choice(5)),
+    ("choice_7", Action::Choice(6)),
+    ("choice_8", Action::Choice(7)),
+    ("choice_9", Action::Choice(8)),
+];
+
+/// Keys that can be bound, named as in the settings
+static KEYS: [KeyCode; 70] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Escape, KeyCode::Return, KeyCode::NumpadEnter, KeyCode::Tab, KeyCode::Space,
+    KeyCode::Back, KeyCode::Delete, KeyCode::Insert, KeyCode::Home, KeyCode::End,
+    KeyCode::PageUp, KeyCode::PageDown, KeyCode::Left, KeyCode::Right, KeyCode::Up,
+    KeyCode::Down, KeyCode::Grave, KeyCode::Minus, KeyCode::Equals, KeyCode::Pause,
+    KeyCode::Snapshot, KeyCode::Scroll,
+];
+
+/// Gamepad buttons that can be bound, named as in the settings
+static BUTTONS: [Button; 17] = [
+    Button::South, Button::East, Button::North, Button::West,
+    Button::LeftTrigger, Button::LeftTrigger2, Button::RightTrigger, Button::RightTrigger2,
+    Button::Select, Button::Start, Button::Mode, Button::LeftThumb, Button::RightThumb,
+    Button::DPadUp, Button::DPadDown, Button::DPadLeft, Button::DPadRight,
+];
+
+impl Action {
+    /// Name of the action in the settings
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(_, action)| *action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, action)| *action)
+    }
+
+    /// Every action, in the order of the settings
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(_, action)| *action)
+    }
+
+    /// Whether holding the key down repeats the action
+    pub fn repeats(self) -> bool {
+        !matches!(
+            self,
+            Action::Fullscreen | Action::Mute | Action::Keyboard | Action::Menu | Action::Choice(_)
+        )
+    }
+}
+
+/// Name of `key` in the settings, like `PageUp` or `F11`
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.iter().copied().find(|key| key_name(*key) == name)
+}
+
+/// Whether `key` can be bound to `action`.
+///
+/// Keys typing a character are kept for the prompt, only the decisions
+/// take them since they are picked at an empty prompt, where the
+/// character is dropped.
+pub fn is_bindable(action: Action, key: KeyCode) -> bool {
+    KEYS.contains(&key) && (!types_text(key) || matches!(action, Action::Choice(_)))
+}
+
+/// Whether `key` types a character at the prompt
+pub(super) fn types_text(key: KeyCode) -> bool {
+    // Letters and digits come first in the bindable keys
+    KEYS[..36].contains(&key)
+        || matches!(key, KeyCode::Space | KeyCode::Minus | KeyCode::Equals | KeyCode::Grave)
+}
+
+/// Name of `button` in the settings, like `South` or `DPadUp`
+pub fn button_name(button: Button) -> String {
+    format!("{:?}", button)
+}
+
+pub fn button_from_name(name: &str) -> Option<Button> {
+    BUTTONS.iter().copied().find(|button| button_name(*button) == name)
+}
+
+/// Keys and gamepad buttons bound to each action
+///
+/// Starts from the default bindings, the settings replace the bindings of
+/// the actions they list. A key or button is bound to a single action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    keys: HashMap<KeyCode, Action>,
+    buttons: HashMap<Button, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = [
+            (KeyCode::Return, Action::Submit),
+            (KeyCode::NumpadEnter, Action::Submit),
+            (KeyCode::Tab, Action::Complete),
+            (KeyCode::Escape, Action::Skip),
+            (KeyCode::PageUp, Action::ScrollUp),
+            (KeyCode::PageDown, Action::ScrollDown),
+            (KeyCode::F11, Action::Fullscreen),
+            (KeyCode::F10, Action::Mute),
+            (KeyCode::F1, Action::Menu),
+            (KeyCode::Key1, Action::Choice(0)),
+            (KeyCode::Key2, Action::Choice(1)),
+            (KeyCode::Key3, Action::Choice(2)),
+            (KeyCode::Key4, Action::Choice(3)),
+            (KeyCode::Key5, Action::Choice(4)),
+            (KeyCode::Key6, Action::Choice(5)),
+            (KeyCode::Key7, Action::Choice(6)),
+            (KeyCode::Key8, Action::Choice(7)),
+            (KeyCode::Key9, Action::Choice(8)),
+        ];
+        let buttons = [
+            (Button::DPadUp, Action::Up),
+            (Button::DPadDown, Action::Down),
+            (Button::DPadLeft, Action::Left),
+            (Button::DPadRight, Action::Right),
+            (Button::South, Action::Confirm),
+            (Button::East, Action::Skip),
+            (Button::North, Action::Keyboard),
+            (Button::Start, Action::Submit),
+        ];
+
+        Self {
+            keys: keys.into_iter().collect(),
+            buttons: buttons.into_iter().collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Default bindings overridden by the `keys` and `buttons` read from
+    /// the settings, both listing key names by action name.
+    pub fn new(
+        keys: &BTreeMap<String, Vec<String>>,
+        buttons: &BTreeMap<String, Vec<String>>,
+    ) -> GameResult<Self> {
+        let mut keymap = Self::default();
+        keymap.keys = check_keys(rebind(&keymap.keys, keys, "key", key_from_name, key_name)?)?;
+        keymap.buttons = rebind(&keymap.buttons, buttons, "button", button_from_name, button_name)?;
+        Ok(keymap)
+    }
+
+    /// These bindings with the keys of the actions listed in `keys`
+    /// replaced, as `new` does.
+    pub fn with_keys(&self, keys: &BTreeMap<String, Vec<String>>) -> GameResult<Self> {
+        Ok(Self {
+            keys: check_keys(rebind(&self.keys, keys, "key", key_from_name, key_name)?)?,
+            buttons: self.buttons.clone(),
+        })
+    }
+
+    /// Action bound to `key`
+    pub fn key_action(&self, key: KeyCode) -> Option<Action> {
+        self.keys.get(&key).copied()
+    }
+
+    /// Action bound to the gamepad `button`
+    pub fn button_action(&self, button: Button) -> Option<Action> {
+        self.buttons.get(&button).copied()
+    }
+
+    /// Keys bound to `action`, in the order of the key names
+    pub fn keys_of(&self, action: Action) -> Vec<KeyCode> {
+        KEYS.iter()
+            .copied()
+            .filter(|key| self.key_action(*key) == Some(action))
+            .collect()
+    }
+
+    /// Bind `key` alone to `action`.
+    ///
+    /// Fails with the other action when the key is already bound to one,
+    /// leaving the bindings unchanged.
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        match self.key_action(key) {
+            Some(other) if other != action => Err(other),
+            _ => {
+                self.keys.retain(|_, a| *a != action);
+                self.keys.insert(key, action);
+                Ok(())
+            }
+        }
+    }
+
+    /// Key names bound to every action, as read by `with_keys`
+    pub fn key_names(&self) -> BTreeMap<String, Vec<String>> {
+        Action::all()
+            .map(|action| {
+                let keys = self.keys_of(action).into_iter().map(key_name).collect();
+                (action.name().to_string(), keys)
+            })
+            .collect()
+    }
+}
+
+/// Replace the bindings of the actions listed in `names` by the inputs
+/// they name.
+///
+/// Fails when an input would end up bound to two actions.
+fn rebind<T: Copy + Eq + Hash>(
+    bindings: &HashMap<T, Action>,
+    names: &BTreeMap<String, Vec<String>>,
+    kind: &str,
+    parse: fn(&str) -> Option<T>,
+    name_of: fn(T) -> String,
+) -> GameResult<HashMap<T, Action>> {
+    let mut replaced = Vec::new();
+    let mut listed = Vec::new();
+    for (action, inputs) in names.iter() {
+        let action = parse_action(action)?;
+        replaced.push(action);
+        for input in inputs.iter() {
+            listed.push((parse(input).ok_or_else(|| unknown(kind, input))?, action));
+        }
+    }
+
+    // Drop every replaced binding first, so inputs can move between actions
+    let mut bindings = bindings.clone();
+    bindings.retain(|_, action| !replaced.contains(action));
+    for (input, action) in listed {
+        match bindings.insert(input, action) {
+            Some(other) if other != action => {
+                return Err(GameError::ConfigError(format!(
+                    "The {} `{}` is bound to both `{}` and `{}`",
+                    kind,
+                    name_of(input),
+                    other.name(),
+                    action.name()
+                )))
+            }
+            _ => (),
+        }
+    }
+    Ok(bindings)
+}
+
+/// Fail when a key typing a character is bound to an action using it,
+/// see `is_bindable`.
+fn check_keys(keys: HashMap<KeyCode, Action>) -> GameResult<HashMap<KeyCode, Action>> {
+    match keys.iter().find(|(key, action)| !is_bindable(**action, **key)) {
+        Some((key, action)) => Err(GameError::ConfigError(format!(
+            "The key `{}` types text and can't be bound to `{}`",
+            key_name(*key),
+            action.name()
+        ))),
+        None => Ok(keys),
+    }
+}
+
+fn parse_action(name: &str) -> GameResult<Action> {
+    Action::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = ACTIONS.iter().map(|(name, _)| *name).collect();
+        GameError::ConfigError(format!(
+            "Unknown action `{}` in the key bindings, actions are: {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+fn unknown(kind: &str, name: &str) -> GameError {
+    GameError::ConfigError(format!("Unknown {} `{}` in the key bindings", kind, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(action, keys)| {
+                (action.to_string(), keys.iter().map(|key| key.to_string()).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn settings_replace_the_default_bindings() {
+        let keys = bindings(&[("skip", &["F3", "F2"])]);
+        let buttons = bindings(&[("confirm", &["West"])]);
+        let keymap = Keymap::new(&keys, &buttons).unwrap();
+
+        assert_eq!(keymap.key_action(KeyCode::F3), Some(Action::Skip));
+        assert_eq!(keymap.key_action(KeyCode::F2), Some(Action::Skip));
+        assert_eq!(keymap.key_action(KeyCode::Escape), None);
+        assert_eq!(keymap.key_action(KeyCode::Return), Some(Action::Submit));
+        assert_eq!(keymap.button_action(Button::West), Some(Action::Confirm));
+        assert_eq!(keymap.button_action(Button::South), None);
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let empty = BTreeMap::new();
+        let err = Keymap::new(&bindings(&[("jump", &["Space"])]), &empty).unwrap_err();
+        assert!(err.to_string().contains("Unknown action `jump`"));
+
+        let err = Keymap::new(&bindings(&[("skip", &["Esc"])]), &empty).unwrap_err();
+        assert!(err.to_string().contains("Unknown key `Esc`"));
+    }
+
+    #[test]
+    fn keys_bound_twice_are_rejected() {
+        let empty = BTreeMap::new();
+        let err = Keymap::new(&bindings(&[("skip", &["F10"])]), &empty).unwrap_err();
+        assert!(err.to_string().contains("`F10` is bound to both `mute` and `skip`"));
+
+        // Keys can move between actions listed together
+        let keys = bindings(&[("mute", &["F9"]), ("skip", &["F10"])]);
+        let keymap = Keymap::new(&keys, &empty).unwrap();
+        assert_eq!(keymap.key_action(KeyCode::F10), Some(Action::Skip));
+    }
+
+    #[test]
+    fn keys_typing_text_only_pick_decisions() {
+        assert!(!is_bindable(Action::Menu, KeyCode::M));
+        assert!(!is_bindable(Action::Skip, KeyCode::Space));
+        assert!(!is_bindable(Action::Submit, KeyCode::Key1));
+        assert!(is_bindable(Action::Choice(0), KeyCode::Key1));
+        assert!(is_bindable(Action::Menu, KeyCode::F2));
+        assert!(!is_bindable(Action::Menu, KeyCode::LShift));
+
+        let empty = BTreeMap::new();
+        let err = Keymap::new(&bindings(&[("menu", &["M"])]), &empty).unwrap_err();
+        assert!(err.to_string().contains("`M` types text and can't be bound to `menu`"));
+    }
+
+    #[test]
+    fn rebound_keys_round_trip() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.bind_key(Action::Skip, KeyCode::Return), Err(Action::Submit));
+        keymap.bind_key(Action::Skip, KeyCode::F2).unwrap();
+
+        let restored = Keymap::default().with_keys(&keymap.key_names()).unwrap();
+        assert_eq!(restored, keymap);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for (name, action) in ACTIONS.iter() {
+            assert_eq!(Action::from_name(action.name()), Some(*action));
+            assert_eq!(action.name(), *name);
+        }
+        for key in KEYS {
+            assert_eq!(key_from_name(&key_name(key)), Some(key));
+        }
+        assert_eq!(button_from_name("DPadUp"), Some(Button::DPadUp));
+    }
+}
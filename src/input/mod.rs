@@ -0,0 +1,7 @@
+mod keymap;
+mod queue;
+mod saved;
+
+pub use keymap::{is_bindable, key_name, Action, Keymap};
+pub use queue::{InputEvent, InputQueue};
+pub use saved::SavedKeys;
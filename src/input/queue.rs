@@ -0,0 +1,160 @@
+use std::mem;
+
+use ggez::event::{Button, KeyCode, KeyMods, MouseButton};
+
+use crate::utils::Point2D;
+
+use super::keymap::{types_text, Action, Keymap};
+
+/// Input received from the window since the last update
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    /// A key or button bound to an action was pressed
+    Action(Action),
+
+    /// A key with no action was pressed, used to edit the prompt
+    Key(KeyCode, KeyMods),
+
+    /// A character was typed
+    Text(char),
+
+    /// Character typed by a key bound to an action, after that action
+    ActionText(char),
+
+    /// The mouse moved to a position in the window
+    MouseMove(Point2D),
+
+    /// The left mouse button was pressed at a position in the window
+    Click(Point2D),
+
+    /// The mouse wheel turned by some lines, up is positive
+    Wheel(f32),
+}
+
+/// Events of the window queued in order until the next update
+///
+/// Keys and gamepad buttons are turned into actions through the keymap,
+/// so nothing is lost when several are pressed in the same frame.
+pub struct InputQueue {
+    keymap: Keymap,
+    events: Vec<InputEvent>,
+
+    /// A key typing text was turned into an action, its character is still
+    /// to come
+    action_typing: bool,
+}
+
+impl InputQueue {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            events: Vec::new(),
+            action_typing: false,
+        }
+    }
+
+    /// Use the bindings of `keymap` from now on.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn key_down(&mut self, key: KeyCode, mods: KeyMods, repeat: bool) {
+        match self.keymap.key_action(key) {
+            Some(action) if repeat && !action.repeats() => (),
+            Some(action) => {
+                self.events.push(InputEvent::Action(action));
+                self.action_typing = types_text(key);
+            }
+            None => self.events.push(InputEvent::Key(key, mods)),
+        }
+    }
+
+    /// Queue a typed character, unless it comes from a shortcut held with
+    /// `Ctrl` or is a control character.
+    pub fn text(&mut self, character: char, mods: KeyMods) {
+        let action_typing = mem::take(&mut self.action_typing);
+        if mods.contains(KeyMods::CTRL) || character.is_control() {
+            return;
+        }
+        self.events.push(match action_typing {
+            true => InputEvent::ActionText(character),
+            false => InputEvent::Text(character),
+        });
+    }
+
+    pub fn button(&mut self, button: Button) {
+        if let Some(action) = self.keymap.button_action(button) {
+            self.events.push(InputEvent::Action(action));
+        }
+    }
+
+    pub fn mouse_motion(&mut self, pos: Point2D) {
+        self.events.push(InputEvent::MouseMove(pos));
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, pos: Point2D) {
+        if button == MouseButton::Left {
+            self.events.push(InputEvent::Click(pos));
+        }
+    }
+
+    pub fn wheel(&mut self, lines: f32) {
+        self.events.push(InputEvent::Wheel(lines));
+    }
+
+    /// Events queued since the last call, oldest first
+    pub fn take(&mut self) -> Vec<InputEvent> {
+        mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_every_event_of_a_frame() {
+        let mut queue = InputQueue::new(Keymap::default());
+        queue.text('l', KeyMods::NONE);
+        queue.text('s', KeyMods::NONE);
+        queue.key_down(KeyCode::Left, KeyMods::SHIFT, false);
+        queue.key_down(KeyCode::Return, KeyMods::NONE, false);
+        queue.button(Button::South);
+
+        assert_eq!(
+            queue.take(),
+            vec![
+                InputEvent::Text('l'),
+                InputEvent::Text('s'),
+                InputEvent::Key(KeyCode::Left, KeyMods::SHIFT),
+                InputEvent::Action(Action::Submit),
+                InputEvent::Action(Action::Confirm),
+            ]
+        );
+        assert!(queue.take().is_empty());
+    }
+
+    #[test]
+    fn drops_shortcut_text_and_toggle_repeats() {
+        let mut queue = InputQueue::new(Keymap::default());
+        queue.text('w', KeyMods::CTRL);
+        queue.text('\r', KeyMods::NONE);
+        queue.key_down(KeyCode::F11, KeyMods::NONE, true);
+        queue.key_down(KeyCode::PageUp, KeyMods::NONE, true);
+        queue.mouse_button(MouseButton::Right, Point2D::new(1.0, 2.0));
+
+        assert_eq!(queue.take(), vec![InputEvent::Action(Action::ScrollUp)]);
+    }
+
+    #[test]
+    fn marks_the_text_of_keys_bound_to_actions() {
+        let mut queue = InputQueue::new(Keymap::default());
+        queue.key_down(KeyCode::Key1, KeyMods::NONE, false);
+        assert_eq!(queue.take(), vec![InputEvent::Action(Action::Choice(0))]);
+
+        // The character may only come with the next frame
+        queue.text('1', KeyMods::NONE);
+        queue.text('1', KeyMods::NONE);
+        assert_eq!(queue.take(), vec![InputEvent::ActionText('1'), InputEvent::Text('1')]);
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ggez::{filesystem, Context, GameError, GameResult};
+
+static KEYS_FILE: &str = "keys.toml";
+
+/// Keys rebound in game, stored in the user's config directory
+///
+/// They apply over the bindings of the settings, which stay the defaults
+/// the options screen can go back to.
+pub struct SavedKeys {
+    path: PathBuf,
+}
+
+impl SavedKeys {
+    pub fn new(ctx: &Context) -> Self {
+        Self {
+            path: filesystem::user_config_dir(ctx).join(KEYS_FILE),
+        }
+    }
+
+    /// Key names bound to each action, `None` when nothing was rebound.
+    pub fn load(&self) -> GameResult<Option<BTreeMap<String, Vec<String>>>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let source = fs::read_to_string(&self.path)?;
+        let keys = toml::from_str(&source).map_err(|err| {
+            GameError::ResourceLoadError(format!("{}: {}", self.path.display(), err))
+        })?;
+        Ok(Some(keys))
+    }
+
+    pub fn save(&self, keys: &BTreeMap<String, Vec<String>>) -> GameResult {
+        let source = toml::to_string(keys)
+            .map_err(|err| GameError::CustomError(err.to_string()))?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, source)?;
+        Ok(())
+    }
+
+    /// Forget the rebound keys, if any.
+    pub fn clear(&self) -> GameResult {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
@@ -1,68 +1,203 @@
 pub mod gui;
 mod utils;
+mod audio;
+mod input;
+mod cli;
 mod dialogue;
+mod save;
+mod settings;
+mod theme;
 
-use std::fs::File;
-use std::path::{self, PathBuf};
+use std::fs::{self, File};
+use std::path::Path;
+use std::process;
 
-use ggez::conf::Conf;
+use clap::Parser;
+use ggez::conf::{Conf, FullscreenType};
 use ggez::event::{self, EventHandler};
-use ggez::graphics::{self, Color};
-use ggez::{Context, ContextBuilder, GameResult};
+use ggez::graphics::{self, Color, Rect};
+use ggez::input::keyboard;
+use ggez::event::EventLoop;
+use ggez::{Context, ContextBuilder, GameError, GameResult};
 
+use cli::Options;
+use dialogue::{Simulation, StoryFile};
 use gui::GUI;
+use input::{Action, InputEvent, InputQueue};
+use utils::Point2D;
+use settings::GameSettings;
+use theme::Theme;
 
-static DEFAULT_BACKGROUND_COL: Color = Color::BLACK;
-static SETTINGS_PATH: &str = "Settings.toml";
-static STATIC_FILES_PATH: &str = "./static";
+static SIMULATION_COLUMNS: usize = 80;
+/// Seed of the terminal id in simulations, fixed so transcripts are stable
+static SIMULATION_SEED: u64 = 0;
 
-fn get_config() -> GameResult<Conf> {
-    let mut config_file = File::open(SETTINGS_PATH)?;
+fn get_config(path: &Path) -> GameResult<Conf> {
+    let mut config_file = File::open(path)?;
     let conf = Conf::from_toml_file(&mut config_file)?;
 
     Ok(conf)
 }
 
-fn get_resources_dir() -> PathBuf {
-    path::PathBuf::from(STATIC_FILES_PATH)
+/// Make a context, without sound when no audio device can be opened.
+///
+/// Also returns whether the context plays sound.
+fn build_context(conf: Conf, resources_dir: &Path) -> GameResult<(Context, EventLoop<()>, bool)> {
+    let builder = |conf: Conf| {
+        ContextBuilder::new("selenialSector", "Vlad")
+            .default_conf(conf)
+            .add_resource_path(resources_dir)
+            .build()
+    };
+
+    let audio = conf.modules.audio;
+    match builder(conf.clone()) {
+        Err(GameError::AudioError(err)) => {
+            eprintln!("Playing without sound: {}", err);
+            let mut conf = conf;
+            conf.modules.audio = false;
+            let (ctx, event_loop) = builder(conf)?;
+            Ok((ctx, event_loop, false))
+        }
+        result => result.map(|(ctx, event_loop)| (ctx, event_loop, audio)),
+    }
 }
 
-fn main() -> GameResult {
-    // Make a Context.
-    let (mut ctx, event_loop) = ContextBuilder::new("selenialSector", "Vlad")
-        .default_conf(get_config()?)
-        .add_resource_path(get_resources_dir())
-        .build()
-        .expect("Could not create game context");
+/// Play the story with the commands of `script_path`, one per line, and
+/// print the transcript without opening a window.
+fn simulate(
+    script_path: &Path,
+    story: &StoryFile,
+    settings: &GameSettings,
+    login: bool,
+) -> GameResult {
+    let script = fs::read_to_string(script_path)?;
+
+    let mut simulation = Simulation::new(story, SIMULATION_COLUMNS, settings.text_speed);
+    if login {
+        simulation = simulation.onboarding(SIMULATION_SEED);
+    }
+    let playthrough = simulation
+        .run(script.lines().filter(|line| !line.trim().is_empty()));
+
+    for line in playthrough.transcript.iter() {
+        println!("{}", line);
+    }
+    for command in playthrough.rejected.iter() {
+        println!("rejected: {}", command);
+    }
+    for (name, value) in playthrough.variables.iter() {
+        println!("{} = {}", name, value);
+    }
+    match playthrough.ending {
+        Some(ending) => println!("ending: {}", ending),
+        None => println!("ending: none"),
+    }
+
+    Ok(())
+}
 
-    let main_state = MainState::new(&mut ctx);
+fn run(options: Options) -> GameResult {
+    let settings_path = options.settings_path()?;
+    let resources_dir = options.resources_dir()?;
+
+    let mut settings = GameSettings::load(&settings_path)?;
+    if let Some(slot) = options.slot {
+        settings.save_slot = slot;
+    }
+
+    let mut story = StoryFile::load(&options.story_path(&resources_dir)?)?;
+    if let Some(start) = &options.start {
+        story.set_start(start)?;
+    }
+
+    if let Some(script_path) = &options.simulate {
+        return simulate(script_path, &story, &settings, options.login);
+    }
+
+    let theme = Theme::load(&resources_dir, &settings.theme)?;
+    let mut conf = get_config(&settings_path)?;
+    options.apply(&mut conf);
+    let fullscreen = conf.window_mode.fullscreen_type != FullscreenType::Windowed;
+
+    let (mut ctx, event_loop, audio) = build_context(conf, &resources_dir)?;
+
+    let main_state = MainState::new(
+        &mut ctx,
+        &settings,
+        &theme,
+        &story,
+        options.start.is_none(),
+        fullscreen,
+        audio,
+    );
 
     // Run!
     event::run(ctx, event_loop, main_state?);
 }
 
+fn main() {
+    if let Err(err) = run(Options::parse()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
 struct MainState {
     gui: GUI,
-    temp: Option<event::KeyCode>,
+    background: Color,
+    input: InputQueue,
+    fullscreen: bool,
 }
 impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<Self> {
+    fn new(
+        ctx: &mut Context,
+        settings: &GameSettings,
+        theme: &Theme,
+        story: &StoryFile,
+        resume: bool,
+        fullscreen: bool,
+        audio: bool,
+    ) -> GameResult<Self> {
+        let gui = GUI::new(ctx, settings, theme, story, resume, audio)?;
+        let input = InputQueue::new(gui.keymap().clone());
         Ok(Self {
-            gui: GUI::new(ctx)?,
-            temp: None,
+            gui,
+            background: theme.background,
+            input,
+            fullscreen,
         })
     }
+
+    /// Switch between the window and desktop fullscreen, the layout
+    /// follows with the resize event.
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        let mode = match self.fullscreen {
+            true => FullscreenType::Windowed,
+            false => FullscreenType::Desktop,
+        };
+        match graphics::set_fullscreen(ctx, mode) {
+            Ok(()) => self.fullscreen = !self.fullscreen,
+            Err(err) => eprintln!("Could not toggle fullscreen: {}", err),
+        }
+    }
 }
 
 impl EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        self.gui.update(ctx, self.temp)?;
-        self.temp = None;
+        let events = self.input.take();
+        if events.contains(&InputEvent::Action(Action::Fullscreen)) {
+            self.toggle_fullscreen(ctx);
+        }
+        self.gui.update(ctx, &events)?;
+        if let Some(keymap) = self.gui.take_keymap() {
+            self.input.set_keymap(keymap);
+        }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, DEFAULT_BACKGROUND_COL);
+        graphics::clear(ctx, self.background);
         self.gui.display(ctx)?;
         graphics::present(ctx)
     }
@@ -71,9 +206,63 @@ impl EventHandler for MainState {
         &mut self,
         _ctx: &mut Context,
         keycode: event::KeyCode,
-        _keymods: event::KeyMods,
-        _repeat: bool,
+        keymods: event::KeyMods,
+        repeat: bool,
+    ) {
+        // The options screen takes the key being rebound, whatever it does
+        if self.gui.is_rebinding() {
+            if !repeat {
+                self.gui.rebind(keycode);
+            }
+            return;
+        }
+        self.input.key_down(keycode, keymods, repeat);
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        if let Err(err) = self.gui.save() {
+            eprintln!("Could not save progress: {}", err);
+        }
+        false
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // Draw in pixels instead of stretching the first window size
+        let result = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height))
+            .and_then(|_| self.gui.resize(ctx));
+        if let Err(err) = result {
+            eprintln!("Could not resize the window: {}", err);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.input.mouse_motion(Point2D::new(x, y));
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
     ) {
-        self.temp = Some(keycode);
+        self.input.mouse_button(button, Point2D::new(x, y));
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.input.wheel(y);
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        btn: event::Button,
+        _id: event::GamepadId,
+    ) {
+        self.input.button(btn);
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        self.input.text(character, keyboard::active_mods(ctx));
     }
 }
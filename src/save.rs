@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ggez::{filesystem, Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+use crate::dialogue::Variables;
+
+/// Version of the save file format written by this build
+pub static SAVE_VERSION: u32 = 4;
+static SAVES_DIR: &str = "saves";
+
+/// Progress of a single playthrough
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+    /// Version of the save file format
+    pub version: u32,
+
+    /// Version of the story file the save was made with
+    pub story_version: u32,
+
+    /// Id of the dialogue node the player is at
+    pub node: String,
+
+    /// Lines already printed on the screen
+    pub history: Vec<String>,
+
+    pub player_name: String,
+    pub terminal_name: String,
+
+    /// Working directory of the shell, missing from version 2 saves
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// In-game time shown by the header clock, in minutes after midnight
+    /// of the first day, missing from version 3 saves
+    #[serde(default)]
+    pub clock: Option<u32>,
+
+    /// Story variables, missing from version 1 saves. Written last, as
+    /// TOML tables follow plain values.
+    #[serde(default)]
+    pub variables: Variables,
+}
+
+/// Numbered save files stored in the user's data directory
+pub struct SaveSlots {
+    dir: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(ctx: &Context) -> Self {
+        Self::in_dir(filesystem::user_data_dir(ctx).join(SAVES_DIR))
+    }
+
+    fn in_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, slot: u32) -> PathBuf {
+        self.dir.join(format!("slot-{}.toml", slot))
+    }
+
+    /// Read the save stored in `slot`.
+    ///
+    /// Returns `None` when the slot is empty. A save that can't be read
+    /// or was written by a newer version of the game is moved aside first,
+    /// so the new game doesn't overwrite it. When it can't be moved, the
+    /// game still starts fresh.
+    pub fn load(&self, slot: u32) -> GameResult<Option<SaveData>> {
+        let path = self.path(slot);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let parsed = fs::read_to_string(&path).map(|source| toml::from_str::<SaveData>(&source));
+        let reason = match parsed {
+            Ok(Ok(data)) if data.version <= SAVE_VERSION => return Ok(Some(data)),
+            Ok(Ok(_)) => "written by a newer version of the game".to_string(),
+            Ok(Err(err)) => err.to_string(),
+            Err(err) => err.to_string(),
+        };
+
+        match self.set_aside(slot) {
+            Ok(backup) => eprintln!(
+                "Could not read save slot {} ({}), moved it to {}",
+                slot,
+                reason,
+                backup.display()
+            ),
+            Err(err) => eprintln!(
+                "Could not read save slot {} ({}) nor move it aside ({}), starting a new game",
+                slot, reason, err
+            ),
+        }
+        Ok(None)
+    }
+
+    /// Rename the file of `slot` to a backup that is not taken yet,
+    /// returns its path.
+    fn set_aside(&self, slot: u32) -> GameResult<PathBuf> {
+        let path = self.path(slot);
+        let backup = (1..)
+            .map(|n| match n {
+                1 => path.with_extension("toml.bak"),
+                n => path.with_extension(format!("toml.bak{}", n)),
+            })
+            .find(|backup| !backup.exists())
+            .unwrap_or_default();
+
+        fs::rename(&path, &backup)?;
+        Ok(backup)
+    }
+
+    /// Write `data` to `slot`, replacing any previous save.
+    pub fn save(&self, slot: u32, data: &SaveData) -> GameResult {
+        let source = toml::to_string(data)
+            .map_err(|err| GameError::CustomError(err.to_string()))?;
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(slot), source)?;
+        Ok(())
+    }
+
+    /// Remove the save stored in `slot`, if any.
+    pub fn clear(&self, slot: u32) -> GameResult {
+        let path = self.path(slot);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slots(name: &str) -> SaveSlots {
+        let dir = std::env::temp_dir().join(format!("selenial-saves-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        SaveSlots::in_dir(dir)
+    }
+
+    fn data(version: u32) -> SaveData {
+        SaveData {
+            version,
+            story_version: 1,
+            node: "start".to_string(),
+            history: vec!["hello".to_string()],
+            player_name: "vlad".to_string(),
+            terminal_name: "c0de-f00d".to_string(),
+            variables: Variables::new(),
+            cwd: Some("/var/log".to_string()),
+            clock: Some(25 * 60),
+        }
+    }
+
+    #[test]
+    fn saves_round_trip() {
+        let slots = slots("round-trip");
+        slots.save(1, &data(SAVE_VERSION)).unwrap();
+
+        let loaded = slots.load(1).unwrap().unwrap();
+        assert_eq!(loaded.node, "start");
+        assert_eq!(loaded.cwd.as_deref(), Some("/var/log"));
+        assert_eq!(loaded.clock, Some(25 * 60));
+        slots.clear(1).unwrap();
+        assert!(slots.load(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn unreadable_saves_are_moved_aside() {
+        let slots = slots("unreadable");
+        fs::write(slots.path(1), "node = ").unwrap();
+        slots.save(2, &data(SAVE_VERSION + 1)).unwrap();
+        fs::write(slots.dir.join("slot-2.toml.bak"), "older backup").unwrap();
+
+        assert!(slots.load(1).unwrap().is_none());
+        assert!(slots.load(2).unwrap().is_none());
+        assert!(!slots.path(1).exists());
+        assert_eq!(fs::read_to_string(slots.dir.join("slot-1.toml.bak")).unwrap(), "node = ");
+        assert_eq!(fs::read_to_string(slots.dir.join("slot-2.toml.bak")).unwrap(), "older backup");
+        assert!(slots.dir.join("slot-2.toml.bak2").exists());
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use ggez::{GameError, GameResult};
+use serde::Deserialize;
+
+/// Game specific settings read from the `[game]` table of the settings file
+///
+/// The window, backend and modules tables of the same file are read by
+/// ggez into its own `Conf`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Maximum number of lines kept in the screen history
+    pub scrollback_lines: usize,
+
+    /// Save slot used to resume and store progress
+    pub save_slot: u32,
+
+    /// Characters per second revealed by the typewriter effect, zero shows
+    /// text at once
+    pub text_speed: f32,
+
+    /// Theme file in the `themes` directory of the resources, without its
+    /// extension
+    pub theme: String,
+
+    /// CRT monitor effect, read from `[game.crt]`
+    pub crt: CrtSettings,
+
+    /// Volumes of the sounds, read from `[game.audio]`
+    pub audio: AudioSettings,
+
+    /// Keys bound to each action, read from `[game.keys]`, replacing the
+    /// default ones of the actions listed. The options screen resets to
+    /// these bindings.
+    pub keys: BTreeMap<String, Vec<String>>,
+
+    /// Gamepad buttons bound to each action, read from `[game.buttons]`
+    pub buttons: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            scrollback_lines: 500,
+            save_slot: 1,
+            text_speed: 40.0,
+            theme: "modern-dark".to_string(),
+            crt: CrtSettings::default(),
+            audio: AudioSettings::default(),
+            keys: BTreeMap::new(),
+            buttons: BTreeMap::new(),
+        }
+    }
+}
+
+/// Strengths of the CRT post-processing effects, zero turns one off
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CrtSettings {
+    /// Draw the interface through the CRT shader
+    pub enabled: bool,
+
+    /// Darkening of every other pixel row, from 0 to 1
+    pub scanlines: f32,
+
+    /// Bulge of the screen towards its corners
+    pub curvature: f32,
+
+    /// Glow added around bright text
+    pub bloom: f32,
+
+    /// Offset in pixels between the red and blue channels
+    pub aberration: f32,
+
+    /// Brightness variation over time, from 0 to 1
+    pub flicker: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanlines: 0.25,
+            curvature: 0.08,
+            bloom: 0.3,
+            aberration: 1.0,
+            flicker: 0.03,
+        }
+    }
+}
+
+/// Volumes of the sounds, each from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Volume applied over every sound
+    pub master_volume: f32,
+
+    /// Volume of the ambient hum
+    pub music_volume: f32,
+
+    /// Volume of the clicks, key presses and story stingers
+    pub effects_volume: f32,
+
+    /// Start without any sound, toggled in game with the `mute` key
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.8,
+            music_volume: 0.4,
+            effects_volume: 0.7,
+            muted: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SettingsFile {
+    #[serde(default)]
+    game: GameSettings,
+}
+
+impl GameSettings {
+    pub fn load(path: &Path) -> GameResult<Self> {
+        let source = fs::read_to_string(path)?;
+        let settings: SettingsFile = toml::from_str(&source)
+            .map_err(|err| GameError::ConfigError(format!("{}: {}", path.display(), err)))?;
+
+        Ok(settings.game)
+    }
+}
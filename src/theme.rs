@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::Path;
+
+use ggez::graphics::Color;
+use ggez::{GameError, GameResult};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+use crate::utils::markup;
+
+static THEMES_DIR: &str = "themes";
+static THEME_EXTENSION: &str = "toml";
+
+/// Colours, border and font of the interface
+///
+/// Themes are read from `themes/<name>.toml` in the resources, colours
+/// written as `#rrggbb` or with the names of the markup colour tags. Values
+/// left out keep the ones of the default theme.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(deserialize_with = "color")]
+    pub background: Color,
+
+    /// Dialogue and stats text
+    #[serde(deserialize_with = "color")]
+    pub text: Color,
+
+    /// Echoed commands and completion matches
+    #[serde(deserialize_with = "color")]
+    pub dim_text: Color,
+
+    /// Terminal info shown before the typed line
+    #[serde(deserialize_with = "color")]
+    pub prompt: Color,
+
+    /// Typed line and caret
+    #[serde(deserialize_with = "color")]
+    pub input: Color,
+
+    #[serde(deserialize_with = "color")]
+    pub border: Color,
+
+    /// Thickness of the border lines in pixels
+    pub border_width: f32,
+
+    /// Font file in the resources, used for every text
+    pub font: String,
+    pub font_size: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color::BLACK,
+            text: Color::WHITE,
+            dim_text: Color::new(0.5, 0.5, 0.5, 1.0),
+            prompt: Color::MAGENTA,
+            input: Color::WHITE,
+            border: Color::WHITE,
+            border_width: 2.0,
+            font: "/fonts/RobotoMono-VariableFont_wght.ttf".to_string(),
+            font_size: 18.0,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    markup::parse_color(&value).ok_or_else(|| D::Error::custom(format!("invalid colour `{}`", value)))
+}
+
+impl Theme {
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    /// Read the theme called `name` from the resources.
+    pub fn load(resources_dir: &Path, name: &str) -> GameResult<Self> {
+        let dir = resources_dir.join(THEMES_DIR);
+        let path = dir.join(name).with_extension(THEME_EXTENSION);
+        if !path.exists() {
+            return Err(GameError::ConfigError(format!(
+                "Theme `{}` not found, available themes: {}",
+                name,
+                Self::available(&dir).join(", ")
+            )));
+        }
+
+        let source = fs::read_to_string(&path)?;
+        Self::parse(&source)
+            .map_err(|err| GameError::ConfigError(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Names of the themes in `dir`, sorted
+    fn available(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+                    .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static THEMES: [&str; 4] = [
+        include_str!("../static/themes/green-phosphor.toml"),
+        include_str!("../static/themes/amber.toml"),
+        include_str!("../static/themes/modern-dark.toml"),
+        include_str!("../static/themes/high-contrast.toml"),
+    ];
+
+    #[test]
+    fn shipped_themes_are_valid() {
+        for source in THEMES.iter() {
+            let theme = Theme::parse(source).unwrap();
+            assert!(theme.font_size > 0.0);
+            assert_ne!(theme.text, theme.background);
+        }
+        assert_eq!(Theme::parse(THEMES[2]).unwrap(), Theme::default());
+    }
+
+    #[test]
+    fn missing_values_keep_the_defaults() {
+        let theme = Theme::parse("text = \"#33ff33\"\nborder = \"green\"").unwrap();
+
+        assert_eq!(theme.text, Color::from_rgb(0x33, 0xff, 0x33));
+        assert_eq!(theme.border, Color::GREEN);
+        assert_eq!(theme.background, Color::BLACK);
+        assert_eq!(theme.font_size, 18.0);
+    }
+
+    #[test]
+    fn invalid_colours_are_rejected() {
+        assert!(Theme::parse("text = \"#33ff\"").is_err());
+        assert!(Theme::parse("text = \"phosphor\"").is_err());
+    }
+
+    #[test]
+    fn lists_the_available_themes() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("static");
+        let err = Theme::load(&dir, "sepia").unwrap_err().to_string();
+
+        assert!(err.contains("amber, green-phosphor, high-contrast, modern-dark"));
+        assert!(Theme::load(&dir, "amber").is_ok());
+    }
+}
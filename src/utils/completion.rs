@@ -0,0 +1,175 @@
+/// Outcome of completing a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// No candidate starts with the typed text
+    None,
+
+    /// Replace the typed text with this line
+    Replace(String),
+
+    /// Several candidates match, replace the typed text with their common
+    /// start and list the words they complete to
+    Ambiguous { line: String, matches: Vec<String> },
+}
+
+/// Shell like completion of typed lines
+///
+/// Candidates are whole lines, such as a command and its arguments, so the
+/// same engine completes commands and arguments. Completing again right
+/// after an ambiguous match cycles through the matching candidates.
+#[derive(Debug, Clone, Default)]
+pub struct Completer {
+    /// Candidates matched by the last ambiguous completion
+    matches: Vec<String>,
+
+    /// Candidate shown by the last cycling completion
+    index: Option<usize>,
+}
+
+impl Completer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop cycling, the next completion starts from the typed text.
+    pub fn reset(&mut self) {
+        self.matches.clear();
+        self.index = None;
+    }
+
+    /// Complete `typed` with the `candidates` it is the start of.
+    pub fn complete(&mut self, typed: &str, candidates: &[String]) -> Completion {
+        if !self.matches.is_empty() {
+            let index = self.index.map_or(0, |index| (index + 1) % self.matches.len());
+            self.index = Some(index);
+            return Completion::Replace(self.matches[index].to_owned());
+        }
+
+        let typed = typed.trim_start().to_lowercase();
+        let mut matches: Vec<String> = candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(&typed))
+            .cloned()
+            .collect();
+        matches.sort();
+        matches.dedup();
+
+        match matches.len() {
+            0 => Completion::None,
+            1 => Completion::Replace(format!("{} ", matches[0])),
+            _ => {
+                // Only the word being completed is listed, like a shell does
+                let word_start = typed.rfind(' ').map_or(0, |pos| pos + 1);
+                let listed = matches
+                    .iter()
+                    .map(|candidate| candidate[word_start..].to_string())
+                    .collect();
+
+                let line = common_prefix(&matches).to_string();
+                self.matches = matches;
+                Completion::Ambiguous {
+                    line,
+                    matches: listed,
+                }
+            }
+        }
+    }
+}
+
+/// Longest start shared by all `lines`
+fn common_prefix(lines: &[String]) -> &str {
+    let first = match lines.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+
+    let mut end = first.len();
+    for line in lines.iter().skip(1) {
+        end = first
+            .char_indices()
+            .zip(line.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((pos, ch), _)| pos + ch.len_utf8())
+            .min(end);
+    }
+    &first[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn single_match_is_completed_with_a_space() {
+        let mut completer = Completer::new();
+        let completion = completer.complete("dr", &candidates(&["drink", "complain"]));
+
+        assert_eq!(completion, Completion::Replace("drink ".to_string()));
+    }
+
+    #[test]
+    fn unknown_text_has_no_completion() {
+        let mut completer = Completer::new();
+
+        assert_eq!(completer.complete("x", &candidates(&["drink"])), Completion::None);
+    }
+
+    #[test]
+    fn ambiguous_matches_complete_the_common_start_then_cycle() {
+        let mut completer = Completer::new();
+        let commands = candidates(&["connect beta", "connect alpha", "cat notes"]);
+
+        assert_eq!(
+            completer.complete("CO", &commands),
+            Completion::Ambiguous {
+                line: "connect ".to_string(),
+                matches: vec!["connect alpha".to_string(), "connect beta".to_string()],
+            }
+        );
+        assert_eq!(
+            completer.complete("connect ", &commands),
+            Completion::Replace("connect alpha".to_string())
+        );
+        assert_eq!(
+            completer.complete("connect alpha", &commands),
+            Completion::Replace("connect beta".to_string())
+        );
+        assert_eq!(
+            completer.complete("connect beta", &commands),
+            Completion::Replace("connect alpha".to_string())
+        );
+    }
+
+    #[test]
+    fn arguments_are_listed_without_the_command() {
+        let mut completer = Completer::new();
+        let commands = candidates(&["cat notes", "cat names"]);
+
+        match completer.complete("cat n", &commands) {
+            Completion::Ambiguous { line, matches } => {
+                assert_eq!(line, "cat n");
+                assert_eq!(matches, vec!["names", "notes"]);
+            }
+            other => panic!("unexpected completion {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reset_stops_cycling() {
+        let mut completer = Completer::new();
+        let commands = candidates(&["milk", "mint"]);
+
+        completer.complete("m", &commands);
+        completer.reset();
+
+        assert!(matches!(
+            completer.complete("mi", &commands),
+            Completion::Ambiguous { .. }
+        ));
+    }
+}
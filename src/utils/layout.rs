@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use super::markup::{LineEnd, Markup, Style, StyledLine};
+
+/// Source of text widths used to wrap lines
+pub trait TextMeasure {
+    /// Width in pixels of `text` when drawn on a single line
+    fn width(&self, text: &str) -> f32;
+}
+
+/// Fixed width measure used to lay out text without a font
+///
+/// Every character takes `advance` pixels, wide (CJK) characters twice as
+/// much, like on a real terminal.
+#[derive(Debug, Clone, Copy)]
+pub struct MonoMeasure {
+    pub advance: f32,
+}
+
+impl TextMeasure for MonoMeasure {
+    fn width(&self, text: &str) -> f32 {
+        text.chars()
+            .map(|ch| match is_wide(ch) {
+                true => self.advance * 2.0,
+                false => self.advance,
+            })
+            .sum()
+    }
+}
+
+/// Step of revealing wrapped text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Char(char, Style),
+    /// Line break written in the text
+    NewLine,
+    /// Line break added to fit the width, `hyphenated` when it splits a word
+    Wrap { hyphenated: bool },
+    Pause(Duration),
+}
+
+/// Wrap parsed markup into lines no wider than `width`.
+///
+/// Lines break between words, on explicit newlines and around wide
+/// (CJK) characters, which can be split anywhere. A word longer than a
+/// whole line is broken with a hyphen.
+pub fn wrap(items: &[Markup], width: f32, measure: &dyn TextMeasure) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut line = String::new();
+    let mut wrapped = false;
+    let mut cursor = 0;
+
+    while cursor < items.len() {
+        match items[cursor] {
+            Markup::Pause(delay) => {
+                steps.push(Step::Pause(delay));
+                cursor += 1;
+                continue;
+            }
+            Markup::Char('\n', _) => {
+                steps.push(Step::NewLine);
+                line.clear();
+                wrapped = false;
+                cursor += 1;
+                continue;
+            }
+            Markup::Char(ch, style) if ch.is_whitespace() => {
+                // Spaces left over from an automatic line break are dropped
+                if !(wrapped && line.is_empty()) {
+                    steps.push(Step::Char(ch, style));
+                    line.push(ch);
+                }
+                cursor += 1;
+                continue;
+            }
+            Markup::Char(..) => (),
+        }
+
+        let word = &items[cursor..cursor + word_len(&items[cursor..])];
+        let word_text: String = word.iter().filter_map(as_char).collect();
+        cursor += word.len();
+
+        if !line.is_empty() && measure.width(&format!("{}{}", line, word_text)) > width {
+            steps.push(Step::Wrap { hyphenated: false });
+            line.clear();
+            wrapped = true;
+        }
+
+        if measure.width(&word_text) <= width {
+            steps.extend(word.iter().map(to_step));
+            line.push_str(&word_text);
+            continue;
+        }
+
+        // The word does not fit on a line of its own, hyphenate it
+        for item in word.iter() {
+            if let Markup::Char(ch, style) = item {
+                let candidate = format!("{}{}-", line, ch);
+                if !line.is_empty() && measure.width(&candidate) > width {
+                    steps.push(Step::Char('-', *style));
+                    steps.push(Step::Wrap { hyphenated: true });
+                    line.clear();
+                }
+                line.push(*ch);
+            }
+            steps.push(to_step(item));
+        }
+    }
+
+    steps
+}
+
+/// Lines of wrapped text, pauses left out.
+pub fn to_lines(steps: &[Step]) -> Vec<StyledLine> {
+    let mut lines = vec![StyledLine::new()];
+    for step in steps {
+        match *step {
+            Step::Char(ch, style) => {
+                if let Some(line) = lines.last_mut() {
+                    line.push(ch, style);
+                }
+            }
+            Step::NewLine => lines.push(StyledLine::new()),
+            Step::Wrap { hyphenated } => {
+                if let Some(line) = lines.last_mut() {
+                    line.end = wrap_end(hyphenated);
+                }
+                lines.push(StyledLine::new());
+            }
+            Step::Pause(_) => (),
+        }
+    }
+    lines
+}
+
+/// Wrap finished lines again to a new `width`.
+///
+/// Lines broken to fit the previous width are joined back first, without
+/// the hyphens added to split words.
+pub fn rewrap(lines: &[StyledLine], width: f32, measure: &dyn TextMeasure) -> Vec<StyledLine> {
+    let mut wrapped = Vec::new();
+    let mut paragraph = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let mut items: Vec<Markup> = line
+            .spans
+            .iter()
+            .flat_map(|span| span.text.chars().map(move |ch| Markup::Char(ch, span.style)))
+            .collect();
+        if line.end == LineEnd::Hyphen {
+            items.pop();
+        }
+        paragraph.extend(items);
+
+        if line.end == LineEnd::Hard || index + 1 == lines.len() {
+            wrapped.extend(to_lines(&wrap(&paragraph, width, measure)));
+            paragraph.clear();
+        }
+    }
+
+    wrapped
+}
+
+/// End of a line broken to fit the width
+pub fn wrap_end(hyphenated: bool) -> LineEnd {
+    match hyphenated {
+        true => LineEnd::Hyphen,
+        false => LineEnd::Wrap,
+    }
+}
+
+/// Number of items making up the word at the start of `items`.
+///
+/// Pauses inside a word belong to it, while a wide character is a word
+/// on its own.
+fn word_len(items: &[Markup]) -> usize {
+    if let Some(Markup::Char(ch, _)) = items.first() {
+        if is_wide(*ch) {
+            return 1;
+        }
+    }
+
+    items
+        .iter()
+        .take_while(|item| match item {
+            Markup::Char(ch, _) => !ch.is_whitespace() && !is_wide(*ch),
+            Markup::Pause(_) => true,
+        })
+        .count()
+        .max(1)
+}
+
+fn as_char(item: &Markup) -> Option<char> {
+    match item {
+        Markup::Char(ch, _) => Some(*ch),
+        Markup::Pause(_) => None,
+    }
+}
+
+fn to_step(item: &Markup) -> Step {
+    match item {
+        Markup::Char(ch, style) => Step::Char(*ch, *style),
+        Markup::Pause(delay) => Step::Pause(*delay),
+    }
+}
+
+/// Whether the character is a wide CJK character that lines may break
+/// around without a space
+pub fn is_wide(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x3FFFD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::markup;
+
+    static MONO: MonoMeasure = MonoMeasure { advance: 1.0 };
+
+    fn lines(source: &str, width: f32) -> Vec<StyledLine> {
+        to_lines(&wrap(&markup::parse(source), width, &MONO))
+    }
+
+    fn texts(lines: &[StyledLine]) -> Vec<String> {
+        lines.iter().map(StyledLine::text).collect()
+    }
+
+    #[test]
+    fn marks_the_breaks_added_to_fit() {
+        let wrapped = lines("tea or milk\nsupercalifragilistic", 8.0);
+
+        assert_eq!(texts(&wrapped), vec!["tea or ", "milk", "superca-", "lifragi-", "listic"]);
+        let ends: Vec<LineEnd> = wrapped.iter().map(|line| line.end).collect();
+        assert_eq!(
+            ends,
+            vec![
+                LineEnd::Wrap,
+                LineEnd::Hard,
+                LineEnd::Hyphen,
+                LineEnd::Hyphen,
+                LineEnd::Hard
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrap_joins_lines_broken_to_fit() {
+        let narrow = lines("tea or [b]milk[/b]\nsupercalifragilistic", 8.0);
+
+        let wide = rewrap(&narrow, 30.0, &MONO);
+        assert_eq!(texts(&wide), vec!["tea or milk", "supercalifragilistic"]);
+        assert!(wide[0].spans.last().is_some_and(|span| span.style.bold));
+        assert_eq!(rewrap(&wide, 8.0, &MONO), narrow);
+    }
+}
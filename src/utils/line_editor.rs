@@ -0,0 +1,290 @@
+/// Editable single line of text, like the prompt of a shell
+///
+/// Keeps the caret position, the previously submitted lines and the last
+/// text deleted with a kill command so it can be yanked back.
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    /// Characters of the line being edited
+    line: Vec<char>,
+
+    /// Insertion point, as a number of characters from the line start
+    caret: usize,
+
+    /// Submitted lines, oldest first
+    history: Vec<String>,
+
+    /// Entry of the history being shown, `None` while editing a new line
+    history_cursor: Option<usize>,
+
+    /// Line being typed before browsing the history
+    draft: Vec<char>,
+
+    /// Text removed by the last kill command
+    killed: String,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> String {
+        self.line.iter().collect()
+    }
+
+    /// Text between the line start and the caret
+    pub fn before_caret(&self) -> String {
+        self.line[..self.caret].iter().collect()
+    }
+
+    /// Insert `text` at the caret, skipping control characters.
+    pub fn insert(&mut self, text: &str) {
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            self.line.insert(self.caret, ch);
+            self.caret += 1;
+        }
+    }
+
+    /// Replace the text before the caret with `text`.
+    pub fn replace_before_caret(&mut self, text: &str) {
+        self.line.drain(..self.caret);
+        self.caret = 0;
+        self.insert(text);
+    }
+
+    /// Delete the character before the caret.
+    pub fn backspace(&mut self) {
+        if self.caret > 0 {
+            self.caret -= 1;
+            self.line.remove(self.caret);
+        }
+    }
+
+    /// Delete the character under the caret.
+    pub fn delete(&mut self) {
+        if self.caret < self.line.len() {
+            self.line.remove(self.caret);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.caret = self.caret.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.caret = (self.caret + 1).min(self.line.len());
+    }
+
+    pub fn home(&mut self) {
+        self.caret = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.caret = self.line.len();
+    }
+
+    /// Delete the word before the caret, like `Ctrl+W`.
+    pub fn kill_word(&mut self) {
+        let mut start = self.caret;
+        while start > 0 && self.line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.kill(start, self.caret);
+    }
+
+    /// Delete everything before the caret, like `Ctrl+U`.
+    pub fn kill_to_start(&mut self) {
+        self.kill(0, self.caret);
+    }
+
+    /// Delete everything after the caret, like `Ctrl+K`.
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.caret, self.line.len());
+    }
+
+    /// Insert the text removed by the last kill at the caret.
+    pub fn yank(&mut self) {
+        let killed = self.killed.to_owned();
+        self.insert(&killed);
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+
+        self.killed = self.line.drain(start..end).collect();
+        self.caret = start;
+    }
+
+    /// Show the previous submitted line.
+    pub fn history_up(&mut self) {
+        let index = match self.history_cursor {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.line.clone();
+                self.history.len() - 1
+            }
+        };
+        self.show_history(Some(index));
+    }
+
+    /// Show the next submitted line, or the line typed before browsing.
+    pub fn history_down(&mut self) {
+        match self.history_cursor {
+            Some(index) if index + 1 < self.history.len() => self.show_history(Some(index + 1)),
+            Some(_) => self.show_history(None),
+            None => (),
+        }
+    }
+
+    fn show_history(&mut self, cursor: Option<usize>) {
+        self.history_cursor = cursor;
+        self.line = match cursor {
+            Some(index) => self.history[index].chars().collect(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.caret = self.line.len();
+    }
+
+    /// Take the line out of the editor and add it to the history.
+    ///
+    /// Returns `None` when nothing but whitespace was typed.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = self.text().trim().to_string();
+        self.line.clear();
+        self.draft.clear();
+        self.caret = 0;
+        self.history_cursor = None;
+
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.to_owned());
+        }
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.insert(text);
+        editor
+    }
+
+    #[test]
+    fn inserts_at_the_caret() {
+        let mut editor = editor("cat");
+        editor.left();
+        editor.left();
+        editor.insert("h");
+        assert_eq!(editor.text(), "chat");
+        assert_eq!(editor.before_caret(), "ch");
+
+        editor.home();
+        editor.delete();
+        editor.end();
+        editor.backspace();
+        assert_eq!(editor.text(), "ha");
+
+        editor.left();
+        editor.replace_before_caret("ch");
+        assert_eq!(editor.text(), "cha");
+        assert_eq!(editor.before_caret(), "ch");
+    }
+
+    #[test]
+    fn caret_stays_in_the_line() {
+        let mut editor = editor("ab");
+        editor.right();
+        assert_eq!(editor.before_caret(), "ab");
+
+        editor.home();
+        editor.left();
+        editor.backspace();
+        assert_eq!(editor.before_caret(), "");
+        assert_eq!(editor.text(), "ab");
+    }
+
+    #[test]
+    fn kills_words_and_yanks_them_back() {
+        let mut editor = editor("connect  remote host ");
+        editor.kill_word();
+        assert_eq!(editor.text(), "connect  remote ");
+
+        editor.kill_word();
+        editor.kill_word();
+        assert_eq!(editor.text(), "");
+
+        editor.yank();
+        assert_eq!(editor.text(), "connect  ");
+    }
+
+    #[test]
+    fn kills_to_line_ends() {
+        let mut editor = editor("drink milk");
+        for _ in 0..4 {
+            editor.left();
+        }
+
+        editor.kill_to_end();
+        assert_eq!(editor.text(), "drink ");
+
+        editor.kill_to_start();
+        assert_eq!(editor.text(), "");
+
+        editor.insert("please ");
+        editor.yank();
+        assert_eq!(editor.text(), "please drink ");
+    }
+
+    #[test]
+    fn browses_history_and_keeps_the_draft() {
+        let mut editor = LineEditor::new();
+        editor.insert("milk");
+        editor.submit();
+        editor.insert("drink");
+        editor.submit();
+        editor.insert("comp");
+
+        editor.history_up();
+        assert_eq!(editor.text(), "drink");
+        editor.history_up();
+        editor.history_up();
+        assert_eq!(editor.text(), "milk");
+
+        editor.history_down();
+        assert_eq!(editor.text(), "drink");
+        editor.history_down();
+        assert_eq!(editor.text(), "comp");
+        assert_eq!(editor.before_caret(), "comp");
+    }
+
+    #[test]
+    fn submit_skips_blank_and_repeated_lines() {
+        let mut editor = LineEditor::new();
+        editor.insert("   ");
+        assert_eq!(editor.submit(), None);
+
+        editor.insert(" tea ");
+        assert_eq!(editor.submit().as_deref(), Some("tea"));
+        editor.insert("tea");
+        editor.submit();
+
+        editor.history_up();
+        editor.history_up();
+        assert_eq!(editor.text(), "tea");
+        editor.history_down();
+        assert_eq!(editor.text(), "");
+    }
+}
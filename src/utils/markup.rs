@@ -0,0 +1,346 @@
+use std::time::Duration;
+
+use ggez::graphics::Color;
+
+/// Named colours accepted by the `[name]...[/name]` tags
+static NAMED_COLORS: [(&str, Color); 9] = [
+    ("red", Color::RED),
+    ("green", Color::GREEN),
+    ("blue", Color::BLUE),
+    ("yellow", Color::YELLOW),
+    ("cyan", Color::CYAN),
+    ("magenta", Color::MAGENTA),
+    ("white", Color::WHITE),
+    ("black", Color::BLACK),
+    ("gray", Color { r: 0.5, g: 0.5, b: 0.5, a: 1.0 }),
+];
+
+/// Visual style of a piece of text
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Style {
+    /// Text colour, the screen default when not set
+    pub color: Option<Color>,
+    pub bold: bool,
+}
+
+/// Run of characters sharing the same style
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// How a line is separated from the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnd {
+    /// Line break written in the text, or the last line
+    #[default]
+    Hard,
+
+    /// Break added to fit the width
+    Wrap,
+
+    /// Break splitting a word, after a hyphen added to the line
+    Hyphen,
+}
+
+/// Single line of text made of styled spans
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StyledLine {
+    pub spans: Vec<Span>,
+    pub end: LineEnd,
+}
+
+impl StyledLine {
+    pub fn new() -> Self {
+        Self {
+            spans: Vec::new(),
+            end: LineEnd::Hard,
+        }
+    }
+
+    /// Append a character, extending the last span when the style matches.
+    pub fn push(&mut self, ch: char, style: Style) {
+        match self.spans.last_mut() {
+            Some(span) if span.style == style => span.text.push(ch),
+            _ => self.spans.push(Span {
+                text: ch.to_string(),
+                style,
+            }),
+        }
+    }
+
+    /// Text of the line without any styling
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Build a line from markup, dropping pauses.
+    pub fn from_markup(source: &str) -> Self {
+        let mut line = Self::new();
+        for item in parse(source) {
+            if let Markup::Char(ch, style) = item {
+                line.push(ch, style);
+            }
+        }
+        line
+    }
+
+    /// Write the line back as markup accepted by `parse`.
+    pub fn to_markup(&self) -> String {
+        let mut markup = String::new();
+        for span in self.spans.iter() {
+            let color = span.style.color.map(color_tag);
+            if let Some(tag) = &color {
+                markup.push_str(&format!("[{}]", tag));
+            }
+            if span.style.bold {
+                markup.push_str("[b]");
+            }
+
+            markup.push_str(&escape(&span.text));
+
+            if span.style.bold {
+                markup.push_str("[/b]");
+            }
+            if color.is_some() {
+                markup.push_str("[/color]");
+            }
+        }
+        markup
+    }
+}
+
+/// Item produced by parsing markup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Markup {
+    /// Visible character and its style
+    Char(char, Style),
+
+    /// Delay before revealing the next character
+    Pause(Duration),
+}
+
+/// Parse text with inline markup.
+///
+/// Supported tags are colours (`[red]...[/red]`, `[color=#ff8800]...[/color]`),
+/// bold (`[b]...[/b]`) and timed pauses (`[pause=500]`, in milliseconds).
+/// `[/color]` closes any colour. `[[` writes a literal `[`, unknown tags and
+/// closing tags that don't match the open one are kept as plain text.
+pub fn parse(source: &str) -> Vec<Markup> {
+    let mut items = Vec::new();
+    // Open colours with the name of their tag, `color` for hex values
+    let mut colors: Vec<(&str, Color)> = Vec::new();
+    let mut bold: usize = 0;
+    let mut rest = source;
+
+    while let Some(ch) = rest.chars().next() {
+        if rest.starts_with("[[") {
+            rest = &rest[2..];
+            items.push(Markup::Char('[', current_style(&colors, bold)));
+            continue;
+        }
+
+        if ch == '[' {
+            if let Some(end) = rest.find(']') {
+                let tag = &rest[1..end];
+                let known = match tag {
+                    "b" => {
+                        bold += 1;
+                        true
+                    }
+                    "/b" if bold > 0 => {
+                        bold -= 1;
+                        true
+                    }
+                    _ if tag.starts_with('/') => {
+                        let name = &tag[1..];
+                        let closes = colors
+                            .last()
+                            .is_some_and(|(open, _)| name == "color" || *open == name);
+                        if closes {
+                            colors.pop();
+                        }
+                        closes
+                    }
+                    _ => match parse_tag_value(tag) {
+                        Some(TagValue::Pause(delay)) => {
+                            items.push(Markup::Pause(delay));
+                            true
+                        }
+                        Some(TagValue::Color(color)) => {
+                            let name = match tag.starts_with("color=") {
+                                true => "color",
+                                false => tag,
+                            };
+                            colors.push((name, color));
+                            true
+                        }
+                        None => false,
+                    },
+                };
+
+                if known {
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        items.push(Markup::Char(ch, current_style(&colors, bold)));
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    items
+}
+
+/// Escape `text` so `parse` shows it as is.
+pub fn escape(text: &str) -> String {
+    text.replace('[', "[[")
+}
+
+enum TagValue {
+    Pause(Duration),
+    Color(Color),
+}
+
+fn parse_tag_value(tag: &str) -> Option<TagValue> {
+    if let Some(delay) = tag.strip_prefix("pause=") {
+        let millis = delay.parse().ok()?;
+        return Some(TagValue::Pause(Duration::from_millis(millis)));
+    }
+
+    if let Some(hex) = tag.strip_prefix("color=#") {
+        return parse_hex(hex).map(TagValue::Color);
+    }
+
+    named_color(tag).map(TagValue::Color)
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    NAMED_COLORS
+        .iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, color)| *color)
+}
+
+/// Colour written as `#rrggbb` or with one of the tag names like `red`
+pub fn parse_color(value: &str) -> Option<Color> {
+    match value.strip_prefix('#') {
+        Some(hex) => parse_hex(hex),
+        None => named_color(value),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::from_rgb_u32(value))
+}
+
+fn color_tag(color: Color) -> String {
+    match NAMED_COLORS.iter().find(|(_, named)| *named == color) {
+        Some((name, _)) => name.to_string(),
+        None => {
+            let (r, g, b) = color.to_rgb();
+            format!("color=#{:02x}{:02x}{:02x}", r, g, b)
+        }
+    }
+}
+
+fn current_style(colors: &[(&str, Color)], bold: usize) -> Style {
+    Style {
+        color: colors.last().map(|(_, color)| *color),
+        bold: bold > 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text of `items` with the colour of each character, pauses as `|`
+    fn colored(items: &[Markup]) -> Vec<(char, Option<Color>)> {
+        items
+            .iter()
+            .map(|item| match item {
+                Markup::Char(ch, style) => (*ch, style.color),
+                Markup::Pause(_) => ('|', None),
+            })
+            .collect()
+    }
+
+    fn text(items: &[Markup]) -> String {
+        colored(items).into_iter().map(|(ch, _)| ch).collect()
+    }
+
+    #[test]
+    fn nested_tags_restore_the_outer_style() {
+        let items = parse("[red]a[b][blue]b[/blue]c[/b][/red]d");
+        assert_eq!(
+            colored(&items),
+            vec![
+                ('a', Some(Color::RED)),
+                ('b', Some(Color::BLUE)),
+                ('c', Some(Color::RED)),
+                ('d', None),
+            ]
+        );
+        let bold: Vec<bool> = items
+            .iter()
+            .map(|item| matches!(item, Markup::Char(_, style) if style.bold))
+            .collect();
+        assert_eq!(bold, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn hex_colors_and_pauses() {
+        let items = parse("[color=#ff8800]a[/color][pause=250]b");
+        assert_eq!(items[0], Markup::Char('a', Style {
+            color: Some(Color::from_rgb(0xff, 0x88, 0x00)),
+            bold: false,
+        }));
+        assert_eq!(items[1], Markup::Pause(Duration::from_millis(250)));
+        assert_eq!(items[2], Markup::Char('b', Style::default()));
+
+        assert_eq!(text(&parse("[color=#ff88]a[pause=soon]")), "[color=#ff88]a[pause=soon]");
+    }
+
+    #[test]
+    fn mismatched_closing_tags_stay_text() {
+        let items = parse("[red]a[/green]b[/red]c[/b]");
+        assert_eq!(text(&items), "a[/green]bc[/b]");
+        assert_eq!(colored(&items)[1], ('[', Some(Color::RED)));
+        assert_eq!(colored(&items)[9], ('b', Some(Color::RED)));
+        assert_eq!(colored(&items)[10], ('c', None));
+
+        // `[/color]` closes a named colour too
+        assert_eq!(colored(&parse("[red]a[/color]b"))[1], ('b', None));
+    }
+
+    #[test]
+    fn escaped_brackets_and_unknown_tags_are_text() {
+        assert_eq!(text(&parse("[[red]a[unknown]")), "[red]a[unknown]");
+        assert_eq!(text(&parse(&escape("[b] stays"))), "[b] stays");
+    }
+
+    #[test]
+    fn lines_round_trip_through_markup() {
+        let sources = [
+            "plain [[text]",
+            "[red]red[/red] and [b]bold[/b]",
+            "[color=#123456][b]both[/b][/color]",
+        ];
+        for source in sources {
+            let line = StyledLine::from_markup(source);
+            assert_eq!(StyledLine::from_markup(&line.to_markup()), line);
+        }
+        assert_eq!(
+            StyledLine::from_markup("[red]a[/red]").to_markup(),
+            "[red]a[/color]"
+        );
+    }
+}
@@ -1,5 +1,15 @@
 mod vector;
 mod textbox;
+mod scrollback;
+mod line_editor;
+mod completion;
+pub mod markup;
+pub mod layout;
 
 pub use vector::Point2D;
 pub use textbox::TextBox;
+pub use scrollback::Scrollback;
+pub use line_editor::LineEditor;
+pub use completion::{Completer, Completion};
+pub use markup::StyledLine;
+pub use layout::TextMeasure;
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use super::StyledLine;
+
+/// Line buffer behind a scrollable text area.
+///
+/// Finished lines are kept in a bounded history, while the `live` lines
+/// belong to text that is still being revealed and are replaced every
+/// frame. The view is described by an `offset` counted in lines from the
+/// newest line, so an offset of zero always follows the end of the text.
+#[derive(Clone, Debug)]
+pub struct Scrollback {
+    /// Finished lines, oldest first
+    lines: VecDeque<StyledLine>,
+
+    /// Lines of the text currently being revealed
+    live: Vec<StyledLine>,
+
+    /// Maximum number of finished lines kept in memory
+    max_lines: usize,
+
+    /// Number of lines the view is scrolled up from the newest line
+    offset: usize,
+}
+
+impl Scrollback {
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            live: Vec::new(),
+            max_lines,
+            offset: 0,
+        }
+    }
+
+    /// Total amount of lines, finished and live
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + self.live.len()
+    }
+
+    /// Finished lines, oldest first
+    pub fn history(&self) -> Vec<StyledLine> {
+        self.lines.iter().cloned().collect()
+    }
+
+    /// Replace the lines of the text currently being revealed.
+    pub fn set_live(&mut self, lines: Vec<StyledLine>) {
+        self.live = lines;
+    }
+
+    /// Move the live lines into the history, returns how many were moved.
+    pub fn commit(&mut self) -> usize {
+        let live = std::mem::take(&mut self.live);
+        let count = live.len();
+        self.extend(live);
+        count
+    }
+
+    /// Append finished lines, dropping the oldest ones over the limit.
+    pub fn extend<I: IntoIterator<Item = StyledLine>>(&mut self, lines: I) {
+        self.lines.extend(lines);
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Replace the finished lines, following the newest line again.
+    pub fn set_history(&mut self, lines: Vec<StyledLine>) {
+        self.lines.clear();
+        self.extend(lines);
+        self.offset = 0;
+    }
+
+    /// Remove every line, finished and live.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.live.clear();
+        self.offset = 0;
+    }
+
+    /// Scroll the view by `delta` lines, positive values going back in
+    /// history. The view never moves past the first line.
+    pub fn scroll(&mut self, delta: isize, rows: usize) {
+        let max_offset = self.line_count().saturating_sub(rows);
+        let offset = self.offset as isize + delta;
+        self.offset = offset.clamp(0, max_offset as isize) as usize;
+    }
+
+    /// Follow the newest line again.
+    pub fn scroll_to_bottom(&mut self) {
+        self.offset = 0;
+    }
+
+    /// Index of the first line in a view of `rows` lines at the current
+    /// offset
+    pub fn first_visible(&self, rows: usize) -> usize {
+        let end = self.line_count().saturating_sub(self.offset);
+        end.saturating_sub(rows)
+    }
+
+    /// Lines that fit in a view of `rows` lines at the current offset.
+    pub fn visible(&self, rows: usize) -> impl Iterator<Item = &StyledLine> {
+        let end = self.line_count().saturating_sub(self.offset);
+        let start = self.first_visible(rows);
+
+        self.lines
+            .iter()
+            .chain(self.live.iter())
+            .skip(start)
+            .take(end - start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrollback(lines: &[&str]) -> Scrollback {
+        let mut scrollback = Scrollback::new(5);
+        scrollback.extend(lines.iter().map(|line| StyledLine::from_markup(line)));
+        scrollback
+    }
+
+    fn visible(scrollback: &Scrollback, rows: usize) -> Vec<String> {
+        scrollback.visible(rows).map(StyledLine::text).collect()
+    }
+
+    #[test]
+    fn scrolling_stops_at_both_ends() {
+        let mut scrollback = scrollback(&["a", "b", "c", "d"]);
+        assert_eq!(visible(&scrollback, 2), vec!["c", "d"]);
+
+        scrollback.scroll(10, 2);
+        assert_eq!(visible(&scrollback, 2), vec!["a", "b"]);
+        assert_eq!(scrollback.first_visible(2), 0);
+
+        scrollback.scroll(-1, 2);
+        assert_eq!(visible(&scrollback, 2), vec!["b", "c"]);
+        scrollback.scroll(-10, 2);
+        assert_eq!(visible(&scrollback, 2), vec!["c", "d"]);
+
+        // A view taller than the text shows all of it
+        scrollback.scroll(3, 6);
+        assert_eq!(visible(&scrollback, 6), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn history_keeps_the_newest_lines() {
+        let mut scrollback = scrollback(&["a", "b", "c", "d"]);
+        scrollback.extend(["e", "f", "g"].map(StyledLine::from_markup));
+        let history: Vec<String> = scrollback.history().iter().map(StyledLine::text).collect();
+        assert_eq!(history, vec!["c", "d", "e", "f", "g"]);
+
+        // Live lines are not counted in the limit until committed
+        scrollback.set_live(vec![StyledLine::from_markup("h")]);
+        assert_eq!(scrollback.line_count(), 6);
+        assert_eq!(scrollback.commit(), 1);
+        assert_eq!(scrollback.line_count(), 5);
+        assert_eq!(scrollback.history()[0].text(), "d");
+    }
+
+    #[test]
+    fn view_stays_in_place_when_lines_are_added() {
+        let mut scrollback = scrollback(&["a", "b", "c", "d"]);
+        scrollback.scroll(1, 2);
+        assert_eq!(scrollback.first_visible(2), 1);
+
+        scrollback.set_live(vec![StyledLine::from_markup("e")]);
+        assert_eq!(scrollback.first_visible(2), 2);
+        assert_eq!(visible(&scrollback, 2), vec!["c", "d"]);
+
+        scrollback.scroll_to_bottom();
+        assert_eq!(visible(&scrollback, 2), vec!["d", "e"]);
+    }
+}
@@ -1,19 +1,18 @@
-use ggez::{
-    graphics::{self, Color, Font, Text},
-    Context, GameResult,
-};
+use std::time::Duration;
 
-use super::Point2D;
+use super::layout::{self, Step, TextMeasure};
+use super::markup::{self, LineEnd, Markup, StyledLine};
 
-type Line = (Point2D, String);
+type Line = StyledLine;
 
 /// Iterable text data source.
 ///
-/// Can be loaded with a text that will be split into chars and words.
-/// Each item returned represents a `(next_char, word_size)`.
+/// Can be loaded with a text that may contain inline markup, see
+/// `markup::parse`. Once laid out for a given width the queue returns the
+/// `Step`s revealing the text: styled characters, line breaks and pauses.
 ///
-/// The `next_char` and `word_size` can be further used to create the
-/// text scrolling effect. Works perfectly with a `TextBox`.
+/// The steps can be further used to create the text scrolling effect.
+/// Works perfectly with a `TextBox`.
 ///
 /// The queue will automatically be cleared once the last item in is
 /// returned by the iterator.
@@ -25,140 +24,209 @@ pub struct TextQueue {
     /// Reading position of the text queue
     cursor: usize,
 
-    /// Data transformed as chars
-    chars: Vec<char>,
+    /// Data parsed as styled chars and pauses
+    items: Vec<Markup>,
 
     /// Shows if the text queue is empty
     is_empty: bool,
 
-    /// Text data split into words
-    words: Vec<String>,
+    /// Data wrapped into lines, empty until the queue is laid out
+    steps: Vec<Step>,
 
-    /// Index position in the list of words
-    words_cursor: usize,
+    /// Shows if the data was wrapped into steps
+    is_laid_out: bool,
 }
 
 impl TextQueue {
     pub fn new() -> Self {
         Self {
             data: String::new(),
-            chars: Vec::new(),
-            cursor: usize::MIN,
+            items: Vec::new(),
+            cursor: 0,
             is_empty: true,
-            words: Vec::new(),
-            words_cursor: usize::MIN,
+            steps: Vec::new(),
+            is_laid_out: false,
         }
     }
 
-    /// Load text data that will be split into chars and words.
+    /// Load text data that will be parsed into styled chars.
     pub fn load(&mut self, data: String) {
         self.data = data;
-        self.chars = self.data.chars().collect::<Vec<char>>();
+        self.items = markup::parse(&self.data);
         self.is_empty = false;
-        self.words = self
-            .data
-            .split(" ")
-            .map(|w| w.to_string())
-            .collect::<Vec<String>>();
-        self.words_cursor = usize::MIN;
+        self.is_laid_out = false;
+    }
+
+    /// Wrap the loaded data into lines no wider than `width`.
+    pub fn layout(&mut self, width: f32, measure: &dyn TextMeasure) {
+        self.steps = layout::wrap(&self.items, width, measure);
+        self.cursor = 0;
+        self.is_laid_out = true;
+    }
+
+    pub fn is_laid_out(&self) -> bool {
+        self.is_laid_out
+    }
+
+    /// Whether every step was returned, or nothing was loaded
+    pub fn is_empty(&self) -> bool {
+        self.is_empty
     }
 
     /// Clears the entire text queue. Would be returned to default.
     pub fn clear(&mut self) {
         self.data.clear();
-        self.chars.clear();
+        self.items.clear();
+        self.steps.clear();
         self.is_empty = true;
-        self.cursor = usize::MIN;
-        self.words_cursor = usize::MIN;
+        self.is_laid_out = false;
+        self.cursor = 0;
+    }
+
+    /// Whether the next step breaks a word, which makes the character
+    /// returned last a hyphen added by the layout
+    fn before_hyphen_break(&self) -> bool {
+        matches!(self.steps.get(self.cursor), Some(Step::Wrap { hyphenated: true }))
     }
 }
 impl Iterator for TextQueue {
-    type Item = (char, usize);
+    type Item = Step;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.is_empty {
+        if self.is_empty || !self.is_laid_out {
             return None;
         }
 
-        if self.cursor >= self.chars.len() && self.cursor > usize::MIN {
+        if self.cursor >= self.steps.len() {
             self.clear();
             return None;
         }
 
-        let next_char = self.chars[self.cursor];
-
-        let word_size: usize = match next_char.is_whitespace() {
-            true => {
-                self.words_cursor += 1;
-                usize::MIN
-            },
-            false => self.words[self.words_cursor].len()
-        };
+        let next_step = self.steps[self.cursor];
         self.cursor += 1;
-        Some((next_char, word_size))
+        Some(next_step)
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct TextBox {
-    /// Represents the 2D position on the display
-    pub display_cursor: Point2D,
-
     /// Represents the lines of the text
     pub lines: Vec<Line>,
 
+    /// Full text shown by the textbox
+    text: String,
+
     /// Width of the textbox in pixels
     width: f32,
 
     /// Represents the line position in the lines array
     lines_cursor: usize,
 
+    /// Characters revealed per second, text is shown at once when zero
+    speed: f32,
 
-    /// Line length index
-    current_line_length: f32,
+    /// Characters owed to the reveal since the last update
+    progress: f32,
 
-    /// Font for the textbox
-    font: Font,
-    font_size: f32,
+    /// Time left before the next character is revealed
+    pause: Duration,
 
-    /// Text space representing a checkpoint to calculate text justify
-    checkpoint: f32,
+    /// Characters revealed by the last update
+    revealed: usize,
 
     /// Iterable data source for text
     pub text_queue: TextQueue
 }
 
 impl TextBox {
-    pub fn new(
-        width: f32,
-        font: Font,
-        font_size: f32,
-        text: String,
-        start_vector: Point2D
-    ) -> Self {
-        let default_line: Line = (start_vector, String::new());
+    pub fn new(width: f32, text: String, speed: f32) -> Self {
         let mut text_queue = TextQueue::new();
-        text_queue.load(text);
+        text_queue.load(text.to_owned());
 
         Self {
             width,
-            lines_cursor: usize::MIN,
-            display_cursor: start_vector,
-            current_line_length: 0.0,
-            lines: vec![default_line],
-            font,
-            font_size,
-            checkpoint: 0.0,
+            text,
+            lines_cursor: 0,
+            lines: vec![Line::new()],
+            speed,
+            progress: 0.0,
+            pause: Duration::ZERO,
+            revealed: 0,
             text_queue
         }
     }
 
+    /// Clear the displayed lines and start revealing the text again.
+    pub fn reset(&mut self) {
+        self.lines = vec![Line::new()];
+        self.lines_cursor = 0;
+        self.progress = 0.0;
+        self.pause = Duration::ZERO;
+        self.text_queue.clear();
+        self.text_queue.load(self.text.to_owned());
+    }
+
+    /// Replace the text and start revealing it from the beginning.
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.reset();
+    }
+
     pub fn new_line(&mut self) {
-        self.display_cursor += Point2D::new(0.0, self.font_size);
-        self.lines.push((self.display_cursor, String::new()));
+        self.lines.push(Line::new());
         self.lines_cursor += 1;
-        self.current_line_length = 0 as f32;
-        self.checkpoint = 0.0;
+    }
+
+    /// Wrap the text to a new `width`, keeping the part already revealed.
+    pub fn resize(&mut self, width: f32, measure: &dyn TextMeasure) {
+        self.width = width;
+
+        let finished = self.text_queue.is_empty();
+        // Only count characters of the text, not the hyphens of split words
+        let hyphens = self.lines.iter().filter(|line| line.end == LineEnd::Hyphen).count()
+            + self.text_queue.before_hyphen_break() as usize;
+        let shown: usize = self.lines.iter().map(|line| line.text().chars().count()).sum();
+        let revealed = shown.saturating_sub(hyphens);
+        self.lines = vec![Line::new()];
+        self.lines_cursor = 0;
+        self.text_queue.load(self.text.to_owned());
+
+        if finished {
+            self.complete(measure);
+            return;
+        }
+        if revealed == 0 {
+            // Laid out with the new width once revealing starts
+            return;
+        }
+
+        self.text_queue.layout(width, measure);
+        let mut shown = 0;
+        while shown < revealed {
+            match self.text_queue.next() {
+                Some(step @ Step::Char(..)) => {
+                    self.show(step);
+                    if !self.text_queue.before_hyphen_break() {
+                        shown += 1;
+                    }
+                }
+                Some(step) => self.show(step),
+                None => break,
+            }
+        }
+    }
+
+    /// Add a revealed step to the lines, pauses are ignored.
+    fn show(&mut self, step: Step) {
+        match step {
+            Step::Char(next_char, style) => self.lines[self.lines_cursor].push(next_char, style),
+            Step::NewLine => self.new_line(),
+            Step::Wrap { hyphenated } => {
+                self.lines[self.lines_cursor].end = layout::wrap_end(hyphenated);
+                self.new_line();
+            }
+            Step::Pause(_) => (),
+        }
     }
 
     pub fn _blank_line(&mut self) {
@@ -166,43 +234,120 @@ impl TextBox {
         self.new_line();
     }
 
-    pub fn update(&mut self) -> Option<()>{
-        let (next_char, word_size) = match self.text_queue.next() {
-            Some(res) => res,
-            None => return None
-        };
-        self.add_checkpoint(word_size);
-        self.add_new_line_if_needed(word_size);
+    /// Reveal the characters due in the `elapsed` time since the last
+    /// update. Returns `None` once the whole text is displayed.
+    ///
+    /// The text is wrapped with `measure` the first time it is revealed.
+    pub fn update(&mut self, elapsed: Duration, measure: &dyn TextMeasure) -> Option<()>{
+        self.revealed = 0;
+        if self.speed <= 0.0 {
+            self.complete(measure);
+            return None;
+        }
+
+        if self.pause > elapsed {
+            self.pause -= elapsed;
+            return Some(());
+        }
+        let elapsed = elapsed - self.pause;
+        self.pause = Duration::ZERO;
+
+        if !self.text_queue.is_laid_out() {
+            self.text_queue.layout(self.width, measure);
+        }
 
-        self.lines[self.lines_cursor].1.push(next_char);
-        self.current_line_length += 10.0;
+        self.progress += elapsed.as_secs_f32() * self.speed;
+        while self.progress >= 1.0 {
+            match self.text_queue.next()? {
+                Step::Pause(delay) => {
+                    self.pause = delay;
+                    self.progress = 0.0;
+                    break;
+                }
+                step @ Step::Char(..) => {
+                    self.show(step);
+                    self.progress -= 1.0;
+                    self.revealed += 1;
+                }
+                step => self.show(step),
+            }
+        }
 
         Some(())
     }
 
-    pub fn display(&self, ctx: &mut Context) -> GameResult {
-        for (cursor, line) in self.lines.iter() {
-            let tx = Text::new((line.clone(), self.font, self.font_size));
-            graphics::draw(ctx, &tx, (cursor.as_vec(), Color::WHITE))?;
-        }
-        Ok(())
+    /// Number of characters revealed by the last `update`
+    pub fn revealed(&self) -> usize {
+        self.revealed
     }
 
-    fn add_new_line_if_needed(&mut self, word_size: usize) {
-        if self.current_line_length > self.width {
-            self.new_line();
-            return
+    /// Reveal the rest of the text at once, ignoring pauses.
+    pub fn complete(&mut self, measure: &dyn TextMeasure) {
+        if !self.text_queue.is_laid_out() {
+            self.text_queue.layout(self.width, measure);
         }
 
-        let sent_length = self.checkpoint + (10.0 * word_size as f32);
-        if sent_length > self.width {
-            self.new_line();
+        while let Some(step) = self.text_queue.next() {
+            self.show(step);
         }
+        self.pause = Duration::ZERO;
+        self.progress = 0.0;
     }
+}
 
-    fn add_checkpoint(&mut self, word_size: usize) {
-        if word_size == 0 {
-            self.checkpoint = self.current_line_length;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::layout::MonoMeasure;
+
+    static MONO: MonoMeasure = MonoMeasure { advance: 1.0 };
+
+    fn texts(textbox: &TextBox) -> Vec<String> {
+        textbox.lines.iter().map(StyledLine::text).collect()
+    }
+
+    #[test]
+    fn resize_keeps_the_revealed_text() {
+        let mut textbox = TextBox::new(8.0, "tea or milk please".to_string(), 1.0);
+        textbox.update(Duration::from_secs(9), &MONO);
+        assert_eq!(texts(&textbox), vec!["tea or ", "mi"]);
+
+        textbox.resize(20.0, &MONO);
+        assert_eq!(texts(&textbox), vec!["tea or mi"]);
+
+        textbox.complete(&MONO);
+        textbox.resize(12.0, &MONO);
+        assert_eq!(texts(&textbox), vec!["tea or milk ", "please"]);
+    }
+
+    #[test]
+    fn resize_skips_the_hyphens_of_split_words() {
+        let mut textbox = TextBox::new(5.0, "abcdefgh ij".to_string(), 1.0);
+        textbox.update(Duration::from_secs(7), &MONO);
+        assert_eq!(texts(&textbox), vec!["abcd-", "ef"]);
+
+        textbox.resize(20.0, &MONO);
+        assert_eq!(texts(&textbox), vec!["abcdef"]);
+
+        // The hyphen is shown but the line is not broken yet
+        let mut textbox = TextBox::new(5.0, "abcdefgh".to_string(), 1.0);
+        textbox.update(Duration::from_secs(5), &MONO);
+        assert_eq!(texts(&textbox), vec!["abcd-"]);
+        textbox.resize(20.0, &MONO);
+        assert_eq!(texts(&textbox), vec!["abcd"]);
+    }
+
+    #[test]
+    fn counts_the_characters_of_each_update() {
+        let mut textbox = TextBox::new(20.0, "ls -la".to_string(), 2.0);
+        textbox.update(Duration::from_secs(2), &MONO);
+        assert_eq!(textbox.revealed(), 4);
+
+        textbox.update(Duration::from_secs(5), &MONO);
+        assert_eq!(textbox.revealed(), 2);
+
+        textbox.update(Duration::from_secs(1), &MONO);
+        assert_eq!(textbox.revealed(), 0);
     }
 }